@@ -0,0 +1,124 @@
+//! BIP-32 derivation of transparent secp256k1 keys along BIP-44 paths
+
+use hmac::{Hmac, Mac};
+use k256::elliptic_curve::{ff::PrimeField, sec1::ToEncodedPoint};
+use k256::{FieldBytes, ProjectivePoint, PublicKey, Scalar, SecretKey};
+use sha2::Sha512;
+
+use crate::OmniError;
+
+/// HMAC-SHA512 key for the master node
+const MASTER_KEY: &[u8] = b"Bitcoin seed";
+
+/// Child indices with this bit set are hardened
+const HARDENED: u32 = 1 << 31;
+
+/// A private key and its chain code
+struct ExtendedPrivateKey {
+    key: SecretKey,
+    chain_code: [u8; 32],
+}
+
+/// A public key and its chain code
+struct ExtendedPublicKey {
+    key: PublicKey,
+    chain_code: [u8; 32],
+}
+
+/// The external key at `m/44'/coin_type'/account'/0/index`
+pub(crate) fn derive_bip44_key(
+    seed: &[u8],
+    coin_type: u32,
+    account: u32,
+    index: u32,
+) -> Result<SecretKey, OmniError> {
+    if coin_type >= HARDENED || account >= HARDENED || index >= HARDENED {
+        return Err(OmniError::InvalidIndex("Path elements must be below 2^31".into()));
+    }
+
+    let path = [44 | HARDENED, coin_type | HARDENED, account | HARDENED, 0, index];
+    let mut node = master(seed)?;
+    for i in path {
+        node = derive_child(&node, i)?;
+    }
+    Ok(node.key)
+}
+
+/// The external public key at `index` below a 65-byte BIP-44 account-level
+/// chain code || compressed public key, i.e. `m/44'/coin_type'/account'/0/index`
+pub(crate) fn derive_external_pubkey(account_key: &[u8], index: u32) -> Result<PublicKey, OmniError> {
+    if account_key.len() != 65 {
+        return Err(OmniError::InvalidLength("Account public key must be 65 bytes".into()));
+    }
+    if index >= HARDENED {
+        return Err(OmniError::InvalidIndex("Address index must be below 2^31".into()));
+    }
+
+    let mut chain_code = [0u8; 32];
+    chain_code.copy_from_slice(&account_key[..32]);
+    let key = PublicKey::from_sec1_bytes(&account_key[32..])
+        .map_err(|_| OmniError::InvalidPoint("Invalid account public key".into()))?;
+
+    let external = derive_public_child(&ExtendedPublicKey { key, chain_code }, 0)?;
+    Ok(derive_public_child(&external, index)?.key)
+}
+
+fn master(seed: &[u8]) -> Result<ExtendedPrivateKey, OmniError> {
+    let i = hmac_sha512(MASTER_KEY, &[seed]);
+    let key = SecretKey::from_slice(&i[..32])
+        .map_err(|_| OmniError::DerivationFailed("Invalid master key".into()))?;
+
+    let mut chain_code = [0u8; 32];
+    chain_code.copy_from_slice(&i[32..]);
+    Ok(ExtendedPrivateKey { key, chain_code })
+}
+
+/// CKDpriv: k_i = parse256(I_L) + k_par (mod n), c_i = I_R
+fn derive_child(parent: &ExtendedPrivateKey, index: u32) -> Result<ExtendedPrivateKey, OmniError> {
+    let i = if index & HARDENED != 0 {
+        hmac_sha512(&parent.chain_code, &[&[0x00], &parent.key.to_bytes(), &index.to_be_bytes()])
+    } else {
+        let public_key = parent.key.public_key().to_encoded_point(true);
+        hmac_sha512(&parent.chain_code, &[public_key.as_bytes(), &index.to_be_bytes()])
+    };
+
+    let child = tweak_scalar(&i)? + parent.key.to_nonzero_scalar().as_ref();
+    let key = SecretKey::from_bytes(&child.to_repr())
+        .map_err(|_| OmniError::DerivationFailed("Derived child key is zero".into()))?;
+
+    let mut chain_code = [0u8; 32];
+    chain_code.copy_from_slice(&i[32..]);
+    Ok(ExtendedPrivateKey { key, chain_code })
+}
+
+/// CKDpub for non-hardened indices: K_i = point(parse256(I_L)) + K_par, c_i = I_R
+fn derive_public_child(parent: &ExtendedPublicKey, index: u32) -> Result<ExtendedPublicKey, OmniError> {
+    let public_key = parent.key.to_encoded_point(true);
+    let i = hmac_sha512(&parent.chain_code, &[public_key.as_bytes(), &index.to_be_bytes()]);
+
+    let tweak = tweak_scalar(&i)?;
+    let child = ProjectivePoint::GENERATOR * tweak + parent.key.to_projective();
+    let key = PublicKey::from_affine(child.to_affine())
+        .map_err(|_| OmniError::DerivationFailed("Derived child key is the identity".into()))?;
+
+    let mut chain_code = [0u8; 32];
+    chain_code.copy_from_slice(&i[32..]);
+    Ok(ExtendedPublicKey { key, chain_code })
+}
+
+/// parse256(I_L), which must be below the curve order
+fn tweak_scalar(i: &[u8; 64]) -> Result<Scalar, OmniError> {
+    Option::from(Scalar::from_repr(*FieldBytes::from_slice(&i[..32])))
+        .ok_or_else(|| OmniError::DerivationFailed("Invalid child key tweak".into()))
+}
+
+fn hmac_sha512(key: &[u8], parts: &[&[u8]]) -> [u8; 64] {
+    let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("HMAC accepts any key length");
+    for part in parts {
+        mac.update(part);
+    }
+
+    let mut output = [0u8; 64];
+    output.copy_from_slice(&mac.finalize().into_bytes());
+    output
+}
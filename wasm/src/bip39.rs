@@ -0,0 +1,124 @@
+//! BIP-39 mnemonic validation and seed derivation
+
+use sha2::{Digest, Sha256, Sha512};
+use unicode_normalization::UnicodeNormalization;
+use wasm_bindgen::prelude::*;
+
+use crate::OmniError;
+
+/// The BIP-39 English wordlist, one word per line in sorted order
+const ENGLISH_WORDLIST: &str = include_str!("bip39_english.txt");
+
+/// PBKDF2-HMAC-SHA512 iteration count
+const PBKDF2_ROUNDS: u32 = 2048;
+
+/// Derive the 64-byte BIP-39 seed from a mnemonic and optional passphrase
+#[wasm_bindgen]
+pub fn mnemonic_to_seed(phrase: &str, passphrase: &str) -> Result<Vec<u8>, OmniError> {
+    let words = normalized_words(phrase);
+    if !is_valid(&words) {
+        return Err(OmniError::InvalidMnemonic("Invalid mnemonic".into()));
+    }
+
+    let password = words.join(" ");
+    let salt = format!("mnemonic{}", passphrase.nfkd().collect::<String>());
+
+    let mut seed = vec![0u8; 64];
+    pbkdf2::pbkdf2_hmac::<Sha512>(password.as_bytes(), salt.as_bytes(), PBKDF2_ROUNDS, &mut seed);
+
+    Ok(seed)
+}
+
+/// Check that a mnemonic uses the English wordlist and has a valid checksum
+#[wasm_bindgen]
+pub fn validate_mnemonic(phrase: &str) -> bool {
+    is_valid(&normalized_words(phrase))
+}
+
+fn normalized_words(phrase: &str) -> Vec<String> {
+    phrase
+        .nfkd()
+        .collect::<String>()
+        .split_whitespace()
+        .map(str::to_owned)
+        .collect()
+}
+
+fn is_valid(words: &[String]) -> bool {
+    // 12, 15, 18, 21 or 24 words, each encoding 11 bits
+    if !matches!(words.len(), 12 | 15 | 18 | 21 | 24) {
+        return false;
+    }
+
+    let wordlist: Vec<&str> = ENGLISH_WORDLIST.lines().collect();
+    let mut bits = Vec::with_capacity(words.len() * 11);
+    for word in words {
+        let index = match wordlist.binary_search(&word.as_str()) {
+            Ok(index) => index,
+            Err(_) => return false,
+        };
+        bits.extend((0..11).rev().map(|i| (index >> i) & 1 == 1));
+    }
+
+    // The last ENT / 32 bits are the leading bits of SHA256(entropy)
+    let checksum_len = bits.len() / 33;
+    let (entropy_bits, checksum_bits) = bits.split_at(bits.len() - checksum_len);
+
+    let entropy: Vec<u8> = entropy_bits
+        .chunks(8)
+        .map(|byte| byte.iter().fold(0u8, |acc, &bit| (acc << 1) | bit as u8))
+        .collect();
+    let hash = Sha256::digest(&entropy);
+
+    checksum_bits
+        .iter()
+        .enumerate()
+        .all(|(i, &bit)| ((hash[i / 8] >> (7 - i % 8)) & 1 == 1) == bit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Trezor's BIP-39 English vectors, all under the passphrase "TREZOR"
+    const VECTORS: [(&str, &str); 5] = [
+        (
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+            "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04",
+        ),
+        (
+            "legal winner thank year wave sausage worth useful legal winner thank yellow",
+            "2e8905819b8723fe2c1d161860e5ee1830318dbf49a83bd451cfb8440c28bd6fa457fe1296106559a3c80937a1c1069be3a3a5bd381ee6260e8d9739fce1f607",
+        ),
+        (
+            "letter advice cage absurd amount doctor acoustic avoid letter advice cage above",
+            "d71de856f81a8acc65e6fc851a38d4d7ec216fd0796d0a6827a3ad6ed5511a30fa280f12eb2e47ed2ac03b5c462a0358d18d69fe4f985ec81778c1b370b652a8",
+        ),
+        (
+            "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo wrong",
+            "ac27495480225222079d7be181583751e86f571027b0497b5b5d11218e0a8a13332572917f0f8e5a589620c6f15b11c61dee327651a14c34e18231052e48c069",
+        ),
+        (
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon \
+             abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon art",
+            "bda85446c68413707090a52022edd26a1c9462295029f2e60cd7c4f2bbd3097170af7a4d73245cafa9c3cca8d561a7c3de6f5d4a10be8ed2a5e608d68f92fcc8",
+        ),
+    ];
+
+    #[test]
+    fn mnemonic_to_seed_matches_vectors() {
+        for (phrase, seed) in VECTORS {
+            assert!(validate_mnemonic(phrase));
+            assert_eq!(hex::encode(mnemonic_to_seed(phrase, "TREZOR").unwrap()), seed);
+        }
+    }
+
+    #[test]
+    fn validate_mnemonic_rejects_bad_checksums_and_words() {
+        let abandon_12 = ["abandon"; 12].join(" ");
+        assert!(!validate_mnemonic(&abandon_12));
+        assert!(!validate_mnemonic("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon zcash"));
+        assert!(!validate_mnemonic("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about"));
+        assert!(matches!(mnemonic_to_seed(&abandon_12, ""), Err(OmniError::InvalidMnemonic(_))));
+    }
+}
@@ -0,0 +1,128 @@
+//! Structured errors surfaced to JavaScript as `{ code, message }` objects
+
+use std::fmt;
+
+use serde::Serialize;
+use wasm_bindgen::JsValue;
+
+/// Error returned by every fallible export
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "code", content = "message")]
+pub enum OmniError {
+    /// An input had the wrong number of bytes
+    InvalidLength(String),
+    /// Bytes were not a canonical scalar, or the scalar is unusable
+    InvalidScalar(String),
+    /// Bytes were not a valid curve point, or the point is unusable
+    InvalidPoint(String),
+    /// A diversifier has no corresponding base point
+    InvalidDiversifier(String),
+    /// A string or serialized structure could not be decoded
+    InvalidEncoding(String),
+    /// A BIP-39 phrase failed validation
+    InvalidMnemonic(String),
+    /// An index or position was out of range
+    InvalidIndex(String),
+    /// A signature could not be parsed
+    InvalidSignature(String),
+    /// A value could not be encoded
+    EncodingFailed(String),
+    /// Note encryption failed
+    EncryptionFailed(String),
+    /// Note decryption failed
+    DecryptionFailed(String),
+    /// Signing failed
+    SigningFailed(String),
+    /// Key derivation produced an unusable key
+    DerivationFailed(String),
+    /// The note commitment tree has no room for another leaf
+    TreeFull(String),
+    /// A value or sum of values exceeded MAX_MONEY
+    Overflow(String),
+    /// Transaction inputs and outputs do not balance
+    Unbalanced(String),
+    /// The platform random number generator failed or is missing
+    RandomnessUnavailable(String),
+    /// A seed was all zeros or repeated a single byte
+    WeakSeed(String),
+}
+
+impl OmniError {
+    /// The error kind, matching the `code` field seen from JavaScript
+    pub fn code(&self) -> &'static str {
+        match self {
+            OmniError::InvalidLength(_) => "InvalidLength",
+            OmniError::InvalidScalar(_) => "InvalidScalar",
+            OmniError::InvalidPoint(_) => "InvalidPoint",
+            OmniError::InvalidDiversifier(_) => "InvalidDiversifier",
+            OmniError::InvalidEncoding(_) => "InvalidEncoding",
+            OmniError::InvalidMnemonic(_) => "InvalidMnemonic",
+            OmniError::InvalidIndex(_) => "InvalidIndex",
+            OmniError::InvalidSignature(_) => "InvalidSignature",
+            OmniError::EncodingFailed(_) => "EncodingFailed",
+            OmniError::EncryptionFailed(_) => "EncryptionFailed",
+            OmniError::DecryptionFailed(_) => "DecryptionFailed",
+            OmniError::SigningFailed(_) => "SigningFailed",
+            OmniError::DerivationFailed(_) => "DerivationFailed",
+            OmniError::TreeFull(_) => "TreeFull",
+            OmniError::Overflow(_) => "Overflow",
+            OmniError::Unbalanced(_) => "Unbalanced",
+            OmniError::RandomnessUnavailable(_) => "RandomnessUnavailable",
+            OmniError::WeakSeed(_) => "WeakSeed",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            OmniError::InvalidLength(message)
+            | OmniError::InvalidScalar(message)
+            | OmniError::InvalidPoint(message)
+            | OmniError::InvalidDiversifier(message)
+            | OmniError::InvalidEncoding(message)
+            | OmniError::InvalidMnemonic(message)
+            | OmniError::InvalidIndex(message)
+            | OmniError::InvalidSignature(message)
+            | OmniError::EncodingFailed(message)
+            | OmniError::EncryptionFailed(message)
+            | OmniError::DecryptionFailed(message)
+            | OmniError::SigningFailed(message)
+            | OmniError::DerivationFailed(message)
+            | OmniError::TreeFull(message)
+            | OmniError::Overflow(message)
+            | OmniError::Unbalanced(message)
+            | OmniError::RandomnessUnavailable(message)
+            | OmniError::WeakSeed(message) => message,
+        }
+    }
+}
+
+impl fmt::Display for OmniError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code(), self.message())
+    }
+}
+
+impl std::error::Error for OmniError {}
+
+impl From<OmniError> for JsValue {
+    fn from(error: OmniError) -> JsValue {
+        serde_wasm_bindgen::to_value(&error).unwrap_or_else(|_| JsValue::from_str(error.message()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::generate_spending_key;
+
+    #[test]
+    fn too_short_seed_is_invalid_length() {
+        let seed: Vec<u8> = (0u8..16).collect();
+        let error = generate_spending_key(&seed).unwrap_err();
+        assert_eq!(error.code(), "InvalidLength");
+
+        // The shape JavaScript sees
+        let json = serde_json::to_value(&error).unwrap();
+        assert_eq!(json["code"], "InvalidLength");
+        assert_eq!(json["message"], error.message());
+    }
+}
@@ -0,0 +1,136 @@
+//! F4Jumble, the unkeyed four-round Feistel permutation of ZIP-316
+
+use std::ops::RangeInclusive;
+
+use wasm_bindgen::prelude::*;
+
+use crate::OmniError;
+
+/// Message lengths F4Jumble is defined for
+const VALID_LENGTH: RangeInclusive<usize> = 48..=4194368;
+
+/// BLAKE2b's maximum digest size, which bounds the left half
+const OUTBYTES: usize = 64;
+
+/// Apply F4Jumble to a message of 48 to 4194368 bytes
+#[wasm_bindgen]
+pub fn f4jumble(data: &[u8]) -> Result<Vec<u8>, OmniError> {
+    let mut message = checked_copy(data)?;
+    let (left, right) = split(&mut message);
+
+    g_round(left, right, 0);
+    h_round(left, right, 0);
+    g_round(left, right, 1);
+    h_round(left, right, 1);
+
+    Ok(message)
+}
+
+/// Invert F4Jumble
+#[wasm_bindgen]
+pub fn f4jumble_inv(data: &[u8]) -> Result<Vec<u8>, OmniError> {
+    let mut message = checked_copy(data)?;
+    let (left, right) = split(&mut message);
+
+    h_round(left, right, 1);
+    g_round(left, right, 1);
+    h_round(left, right, 0);
+    g_round(left, right, 0);
+
+    Ok(message)
+}
+
+fn checked_copy(data: &[u8]) -> Result<Vec<u8>, OmniError> {
+    if !VALID_LENGTH.contains(&data.len()) {
+        return Err(OmniError::InvalidLength(
+            "F4Jumble input must be between 48 and 4194368 bytes".into(),
+        ));
+    }
+    Ok(data.to_vec())
+}
+
+/// The left half is min(64, len / 2) bytes, the right half is the rest
+fn split(message: &mut [u8]) -> (&mut [u8], &mut [u8]) {
+    let left_length = std::cmp::min(OUTBYTES, message.len() / 2);
+    message.split_at_mut(left_length)
+}
+
+/// H_i: left ^= BLAKE2b-ℓ_L("UA_F4Jumble_H" || i || 0 || 0, right)
+fn h_round(left: &mut [u8], right: &[u8], i: u8) {
+    let mut personal = *b"UA_F4Jumble_H\0\0\0";
+    personal[13] = i;
+
+    let hash = blake2b_simd::Params::new()
+        .hash_length(left.len())
+        .personal(&personal)
+        .hash(right);
+    xor(left, hash.as_bytes());
+}
+
+/// G_i: right ^= the concatenation of BLAKE2b-512("UA_F4Jumble_G" || i || I2LEOSP16(j), left)
+fn g_round(left: &[u8], right: &mut [u8], i: u8) {
+    for (j, chunk) in right.chunks_mut(OUTBYTES).enumerate() {
+        let mut personal = *b"UA_F4Jumble_G\0\0\0";
+        personal[13] = i;
+        personal[14..].copy_from_slice(&(j as u16).to_le_bytes());
+
+        let hash = blake2b_simd::Params::new()
+            .hash_length(OUTBYTES)
+            .personal(&personal)
+            .hash(left);
+        xor(chunk, hash.as_bytes());
+    }
+}
+
+fn xor(target: &mut [u8], source: &[u8]) {
+    for (t, s) in target.iter_mut().zip(source) {
+        *t ^= s;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Normal and jumbled messages of 48, 64 and 129 bytes from the ZIP-316
+    /// test vectors
+    const VECTORS: [(&str, &str); 3] = [
+        (
+            "5d7a8f739a2d9e945b0ce152a8049e294c4d6e66b164939daffa2ef6ee6921481cdd86b3cc4318d9614fc820905d042b",
+            "0304d029141b995da5387c125970673504d6c764d91ea6c082123770c7139ccd88ee27368cd0c0921a0444c8e5858d22",
+        ),
+        (
+            "b1ef9ca3f24988c7b3534201cfb1cd8dbf69b8250c18ef41294ca97993db546c1fe01f7e9c8e36d6a5e29d4e30a73594\
+             bf5098421c69378af1e40f64e125946f",
+            "5271fa3321f3adbcfb075196883d542b438ec6339176537daf859841fe6a56222bff76d1662b5509a9e1079e446eeedd\
+             2e683c31aae3ee1851d7954328526be1",
+        ),
+        (
+            "25c9a138f49b1a537edcf04be34a9851a7af9db6990ed83dd64af3597c04323ea51b0052ad8084a8b9da948d320dadd6\
+             4f5431e61ddf658d24ae67c22c8d1309131fc00fe7f235734276d38d47f1e191e00c7a1d48af046827591e9733a97fa6\
+             b679f3dc601d008285edcbdae69ce8fc1be4aac00ff2711ebd931de518856878f7",
+            "7508a3a146714f229db91b543e240633ed57853f6451c9db6d64c6e86af1b88b28704f608582c53c51ce7d5b8548827a\
+             971d2b98d41b7f6258655902440cd66ee11e84dbfac7d2a43696fd0468810a3d9637c3fa58e7d2d341ef250fa09b9fb7\
+             1a78a41d389370138a55ea58fcde779d714a04e0d30e61dc2d8be0da61cd684509",
+        ),
+    ];
+
+    #[test]
+    fn matches_zip316_vectors() {
+        for (normal, jumbled) in VECTORS {
+            let (normal, jumbled) = (hex::decode(normal).unwrap(), hex::decode(jumbled).unwrap());
+            assert_eq!(f4jumble(&normal).unwrap(), jumbled);
+            assert_eq!(f4jumble_inv(&jumbled).unwrap(), normal);
+        }
+    }
+
+    #[test]
+    fn rejects_out_of_range_lengths() {
+        for length in [0, 47, *VALID_LENGTH.end() + 1] {
+            let data = vec![0u8; length];
+            assert!(matches!(f4jumble(&data), Err(OmniError::InvalidLength(_))));
+            assert!(matches!(f4jumble_inv(&data), Err(OmniError::InvalidLength(_))));
+        }
+        assert_eq!(f4jumble_inv(&f4jumble(&[7u8; 48]).unwrap()).unwrap(), [7u8; 48]);
+    }
+}
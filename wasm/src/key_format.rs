@@ -0,0 +1,141 @@
+//! Self-describing Base58Check key backups
+//!
+//! The payload is `version || network || key_type || key`, followed by the
+//! usual 4-byte double-SHA256 Base58Check checksum.
+
+use js_sys::{Object, Reflect, Uint8Array};
+use wasm_bindgen::prelude::*;
+
+use crate::params::NETWORKS;
+use crate::{Network, OmniError};
+
+/// Version byte of the current layout
+const KEY_FORMAT_VERSION: u8 = 0x01;
+
+/// Key types and their lengths
+const KEY_TYPES: [(u8, usize); 7] = [
+    (KEY_TYPE_SAPLING_SPENDING_KEY, 96),
+    (KEY_TYPE_SAPLING_FULL_VIEWING_KEY, 96),
+    (KEY_TYPE_SAPLING_DIVERSIFIABLE_FVK, 128),
+    (KEY_TYPE_SAPLING_EXTENDED_SPENDING_KEY, 169),
+    (KEY_TYPE_ORCHARD_SPENDING_KEY, 32),
+    (KEY_TYPE_ORCHARD_FULL_VIEWING_KEY, 96),
+    (KEY_TYPE_TRANSPARENT_PRIVATE_KEY, 32),
+];
+
+/// Expanded spending key ask || nsk || ovk
+pub const KEY_TYPE_SAPLING_SPENDING_KEY: u8 = 0x01;
+/// ak || nk || ovk
+pub const KEY_TYPE_SAPLING_FULL_VIEWING_KEY: u8 = 0x02;
+/// ak || nk || ovk || dk
+pub const KEY_TYPE_SAPLING_DIVERSIFIABLE_FVK: u8 = 0x03;
+/// ZIP-32 extended spending key
+pub const KEY_TYPE_SAPLING_EXTENDED_SPENDING_KEY: u8 = 0x04;
+pub const KEY_TYPE_ORCHARD_SPENDING_KEY: u8 = 0x05;
+/// ak || nk || rivk
+pub const KEY_TYPE_ORCHARD_FULL_VIEWING_KEY: u8 = 0x06;
+/// secp256k1 private key
+pub const KEY_TYPE_TRANSPARENT_PRIVATE_KEY: u8 = 0x07;
+
+/// Encode a key with its type and network as a checksummed Base58Check string
+#[wasm_bindgen]
+pub fn encode_key_with_checksum(key: &[u8], key_type: u8, network: Network) -> Result<String, OmniError> {
+    check_key_length(key_type, key.len())?;
+
+    let mut payload = Vec::with_capacity(3 + key.len());
+    payload.push(KEY_FORMAT_VERSION);
+    payload.push(network.key_format_byte());
+    payload.push(key_type);
+    payload.extend_from_slice(key);
+
+    Ok(bs58::encode(&payload).with_check().into_string())
+}
+
+/// Decode and validate a string from `encode_key_with_checksum` into
+/// `{ key, keyType, network }`
+#[wasm_bindgen]
+pub fn decode_key_with_checksum(encoded: &str) -> Result<JsValue, OmniError> {
+    let (key, key_type, network) = decode_payload(encoded)?;
+
+    let result = Object::new();
+    Reflect::set(&result, &"key".into(), &Uint8Array::from(&key[..]))
+        .and_then(|_| Reflect::set(&result, &"keyType".into(), &key_type.into()))
+        .and_then(|_| Reflect::set(&result, &"network".into(), &network.into()))
+        .map_err(|_| OmniError::EncodingFailed("Failed to build result object".into()))?;
+    Ok(result.into())
+}
+
+/// The key, key type and network of an `encode_key_with_checksum` string
+fn decode_payload(encoded: &str) -> Result<(Vec<u8>, u8, Network), OmniError> {
+    let payload = bs58::decode(encoded)
+        .with_check(None)
+        .into_vec()
+        .map_err(|e| OmniError::InvalidEncoding(format!("Invalid Base58Check encoding: {}", e)))?;
+
+    let [version, network, key_type, key @ ..] = &payload[..] else {
+        return Err(OmniError::InvalidLength("Encoded key is too short".into()));
+    };
+    if *version != KEY_FORMAT_VERSION {
+        return Err(OmniError::InvalidEncoding(format!("Unsupported key format version {}", version)));
+    }
+    let network = NETWORKS
+        .into_iter()
+        .find(|candidate| candidate.key_format_byte() == *network)
+        .ok_or_else(|| OmniError::InvalidEncoding(format!("Unknown network byte {}", network)))?;
+    check_key_length(*key_type, key.len())?;
+
+    Ok((key.to_vec(), *key_type, network))
+}
+
+fn check_key_length(key_type: u8, length: usize) -> Result<(), OmniError> {
+    let (_, expected) = KEY_TYPES
+        .iter()
+        .find(|(code, _)| *code == key_type)
+        .ok_or_else(|| OmniError::InvalidEncoding(format!("Unknown key type {}", key_type)))?;
+
+    if length != *expected {
+        return Err(OmniError::InvalidLength(format!("Key type {} must be {} bytes", key_type, expected)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_every_network() {
+        let key = [0x5au8; 96];
+        for network in NETWORKS {
+            let encoded = encode_key_with_checksum(&key, KEY_TYPE_SAPLING_SPENDING_KEY, network).unwrap();
+            assert_eq!(
+                decode_payload(&encoded).unwrap(),
+                (key.to_vec(), KEY_TYPE_SAPLING_SPENDING_KEY, network)
+            );
+        }
+    }
+
+    #[test]
+    fn detects_corruption() {
+        let encoded = encode_key_with_checksum(&[7u8; 32], KEY_TYPE_ORCHARD_SPENDING_KEY, Network::Mainnet).unwrap();
+
+        for i in 0..encoded.len() {
+            let mut corrupted = encoded.clone().into_bytes();
+            corrupted[i] = if corrupted[i] == b'2' { b'3' } else { b'2' };
+            let corrupted = String::from_utf8(corrupted).unwrap();
+            assert!(decode_payload(&corrupted).is_err(), "corruption at {} went undetected", i);
+        }
+    }
+
+    #[test]
+    fn rejects_mismatched_length() {
+        assert!(matches!(
+            encode_key_with_checksum(&[7u8; 31], KEY_TYPE_ORCHARD_SPENDING_KEY, Network::Mainnet),
+            Err(OmniError::InvalidLength(_))
+        ));
+        assert!(matches!(
+            encode_key_with_checksum(&[7u8; 32], 0x7f, Network::Mainnet),
+            Err(OmniError::InvalidEncoding(_))
+        ));
+    }
+}
@@ -0,0 +1,557 @@
+//! Typed Sapling spending keys, full viewing keys and payment addresses
+
+use group::GroupEncoding;
+use jubjub::Fr;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+use crate::{
+    bytes_to_scalar, check_seed_entropy, crh_ivk, ct_eq, derive_ak, derive_nk, diversifier_to_point,
+    encode_payment_address, expand_spending_key, find_ff1_diversifier, hex_decode, hex_encode,
+    parse_non_identity_point, parse_subgroup_point, prf_expand, zip32,
+    Network, OmniError,
+};
+
+/// A Sapling expanded spending key: ask || nsk || ovk
+///
+/// The key material is wiped when the struct is dropped (or freed from
+/// JavaScript). Byte arrays returned by `to_bytes` and the getters are copies
+/// owned by JavaScript, which the crate cannot wipe.
+#[wasm_bindgen]
+#[derive(Clone, Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+#[serde(into = "SpendingKeyJson", try_from = "SpendingKeyJson")]
+pub struct SpendingKey {
+    ask: [u8; 32],
+    nsk: [u8; 32],
+    ovk: [u8; 32],
+}
+
+#[wasm_bindgen]
+impl SpendingKey {
+    /// Expand a seed into a spending key
+    ///
+    /// A 32-byte seed is used directly as the spending key; a 64-byte BIP-39
+    /// seed goes through the ZIP-32 master key derivation instead of being truncated.
+    /// Seeds that are all zeros or repeat a single byte are rejected, as they
+    /// usually mean the caller's RNG failed.
+    pub fn from_seed(seed: &[u8]) -> Result<SpendingKey, OmniError> {
+        check_seed_entropy(seed)?;
+
+        let expsk = match seed.len() {
+            32 => Zeroizing::new(expand_spending_key(seed).to_vec()),
+            64 => Zeroizing::new(zip32::derive_master(seed)?.expanded_spending_key()),
+            n => {
+                return Err(OmniError::InvalidLength(format!(
+                    "Seed must be 32 bytes or a 64-byte BIP-39 seed, got {} bytes (pass raw bytes, not a hex string)",
+                    n
+                )))
+            }
+        };
+        SpendingKey::from_bytes(&expsk)
+    }
+
+    /// Decode a 96-byte ask || nsk || ovk encoding
+    pub fn from_bytes(bytes: &[u8]) -> Result<SpendingKey, OmniError> {
+        if bytes.len() != 96 {
+            return Err(OmniError::InvalidLength("Spending key must be 96 bytes".into()));
+        }
+        SpendingKey::from_parts(&bytes[0..32], &bytes[32..64], &bytes[64..96])
+    }
+
+    /// Build a spending key from its components
+    pub fn from_parts(ask: &[u8], nsk: &[u8], ovk: &[u8]) -> Result<SpendingKey, OmniError> {
+        if ask.len() != 32 {
+            return Err(OmniError::InvalidLength("ask must be 32 bytes".into()));
+        }
+        if nsk.len() != 32 {
+            return Err(OmniError::InvalidLength("nsk must be 32 bytes".into()));
+        }
+        if ovk.len() != 32 {
+            return Err(OmniError::InvalidLength("ovk must be 32 bytes".into()));
+        }
+        bytes_to_scalar(ask)?;
+        bytes_to_scalar(nsk)?;
+
+        let mut key = SpendingKey {
+            ask: [0u8; 32],
+            nsk: [0u8; 32],
+            ovk: [0u8; 32],
+        };
+        key.ask.copy_from_slice(ask);
+        key.nsk.copy_from_slice(nsk);
+        key.ovk.copy_from_slice(ovk);
+        Ok(key)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [self.ask, self.nsk, self.ovk].concat()
+    }
+
+    /// A plain object with hex-encoded fields, for persisting as JSON
+    pub fn to_json(&self) -> Result<JsValue, OmniError> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| OmniError::EncodingFailed(e.to_string()))
+    }
+
+    /// Parse the object produced by `to_json`
+    pub fn from_json(value: JsValue) -> Result<SpendingKey, OmniError> {
+        serde_wasm_bindgen::from_value(value).map_err(|e| OmniError::InvalidEncoding(e.to_string()))
+    }
+
+    /// The spend authorizing key
+    #[wasm_bindgen(getter)]
+    pub fn ask(&self) -> Vec<u8> {
+        self.ask.to_vec()
+    }
+
+    /// The proof authorizing key
+    #[wasm_bindgen(getter)]
+    pub fn nsk(&self) -> Vec<u8> {
+        self.nsk.to_vec()
+    }
+
+    /// The outgoing viewing key
+    #[wasm_bindgen(getter)]
+    pub fn ovk(&self) -> Vec<u8> {
+        self.ovk.to_vec()
+    }
+
+    /// ak = [ask] G, nk = [nsk] H, with dk derived from ask as
+    /// `derive_diversifiable_fvk` derives it. A ZIP-32 account's dk comes from
+    /// its extended key instead; use `Account.full_viewing_key` for those.
+    pub fn to_full_viewing_key(&self) -> Result<FullViewingKey, OmniError> {
+        Ok(FullViewingKey {
+            ak: derive_ak(&bytes_to_scalar(&self.ask)?),
+            nk: derive_nk(&bytes_to_scalar(&self.nsk)?),
+            ovk: self.ovk,
+            dk: prf_expand(&self.ask, &[0x10]),
+        })
+    }
+}
+
+/// A Sapling diversifiable full viewing key: ak || nk || ovk || dk
+#[wasm_bindgen]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(into = "FullViewingKeyJson", try_from = "FullViewingKeyJson")]
+pub struct FullViewingKey {
+    ak: [u8; 32],
+    nk: [u8; 32],
+    ovk: [u8; 32],
+    dk: [u8; 32],
+}
+
+#[wasm_bindgen]
+impl FullViewingKey {
+    /// Decode a 128-byte ak || nk || ovk || dk encoding, as
+    /// `derive_diversifiable_fvk` returns and a unified full viewing key's
+    /// Sapling item holds
+    ///
+    /// `derive_viewing_key` also returns 128 bytes, ak || nk || ivk || ovk;
+    /// reading that as this layout would derive addresses with its ovk as dk,
+    /// so a third component equal to CRH^ivk(ak, nk), which a genuine ovk
+    /// matches with negligible probability, is rejected.
+    pub fn from_bytes(bytes: &[u8]) -> Result<FullViewingKey, OmniError> {
+        if bytes.len() != 128 {
+            return Err(OmniError::InvalidLength("Full viewing key must be 128 bytes".into()));
+        }
+        let key = FullViewingKey::from_parts(&bytes[0..32], &bytes[32..64], &bytes[64..96], &bytes[96..128])?;
+        if ct_eq(&key.ovk, &key.ivk()) {
+            return Err(OmniError::InvalidEncoding(
+                "Key is derive_viewing_key's ak || nk || ivk || ovk; decode derive_diversifiable_fvk's \
+                 ak || nk || ovk || dk"
+                    .into(),
+            ));
+        }
+        Ok(key)
+    }
+
+    /// Build a full viewing key from its components
+    pub fn from_parts(ak: &[u8], nk: &[u8], ovk: &[u8], dk: &[u8]) -> Result<FullViewingKey, OmniError> {
+        if ak.len() != 32 {
+            return Err(OmniError::InvalidLength("ak must be 32 bytes".into()));
+        }
+        if nk.len() != 32 {
+            return Err(OmniError::InvalidLength("nk must be 32 bytes".into()));
+        }
+        if ovk.len() != 32 {
+            return Err(OmniError::InvalidLength("ovk must be 32 bytes".into()));
+        }
+        if dk.len() != 32 {
+            return Err(OmniError::InvalidLength("dk must be 32 bytes".into()));
+        }
+        // ak must not be the identity, or any signature would verify under it
+        parse_non_identity_point(ak, "Invalid spend validating key")?;
+        parse_subgroup_point(nk, "Invalid nullifier deriving key")?;
+
+        let mut key = FullViewingKey {
+            ak: [0u8; 32],
+            nk: [0u8; 32],
+            ovk: [0u8; 32],
+            dk: [0u8; 32],
+        };
+        key.ak.copy_from_slice(ak);
+        key.nk.copy_from_slice(nk);
+        key.ovk.copy_from_slice(ovk);
+        key.dk.copy_from_slice(dk);
+        Ok(key)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [self.ak, self.nk, self.ovk, self.dk].concat()
+    }
+
+    /// A plain object with hex-encoded fields, for persisting as JSON
+    pub fn to_json(&self) -> Result<JsValue, OmniError> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| OmniError::EncodingFailed(e.to_string()))
+    }
+
+    /// Parse the object produced by `to_json`
+    pub fn from_json(value: JsValue) -> Result<FullViewingKey, OmniError> {
+        serde_wasm_bindgen::from_value(value).map_err(|e| OmniError::InvalidEncoding(e.to_string()))
+    }
+
+    /// The spend validating key
+    #[wasm_bindgen(getter)]
+    pub fn ak(&self) -> Vec<u8> {
+        self.ak.to_vec()
+    }
+
+    /// The nullifier deriving key
+    #[wasm_bindgen(getter)]
+    pub fn nk(&self) -> Vec<u8> {
+        self.nk.to_vec()
+    }
+
+    /// The outgoing viewing key
+    #[wasm_bindgen(getter)]
+    pub fn ovk(&self) -> Vec<u8> {
+        self.ovk.to_vec()
+    }
+
+    /// The diversifier key
+    #[wasm_bindgen(getter)]
+    pub fn dk(&self) -> Vec<u8> {
+        self.dk.to_vec()
+    }
+
+    /// The incoming viewing key CRH^ivk(ak, nk)
+    #[wasm_bindgen(getter)]
+    pub fn ivk(&self) -> Vec<u8> {
+        crh_ivk(&self.ak, &self.nk).to_bytes().to_vec()
+    }
+
+    /// The payment address at the first diversifier index >= `diversifier_index`
+    /// whose FF1-AES256_dk diversifier is valid, encoded for `network`; the
+    /// same address `derive_payment_address` gives for `to_bytes()`
+    pub fn address(&self, diversifier_index: u32, network: Network) -> Result<String, OmniError> {
+        let (address, _) = self.payment_address(diversifier_index)?;
+        address.encode(network)
+    }
+
+    /// The address at the first valid FF1-AES256_dk diversifier index; the
+    /// same address `derive_default_address` gives for `to_bytes()`
+    pub fn default_address(&self, network: Network) -> Result<String, OmniError> {
+        self.address(0, network)
+    }
+}
+
+impl FullViewingKey {
+    /// The address behind `address`, along with the diversifier index actually used
+    pub(crate) fn payment_address(&self, diversifier_index: u32) -> Result<(PaymentAddress, u32), OmniError> {
+        PaymentAddress::from_ivk(&crh_ivk(&self.ak, &self.nk), &self.dk, diversifier_index)
+    }
+}
+
+/// A Sapling payment address: diversifier || pk_d
+#[wasm_bindgen]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(into = "PaymentAddressJson", try_from = "PaymentAddressJson")]
+pub struct PaymentAddress {
+    diversifier: [u8; 11],
+    pk_d: [u8; 32],
+}
+
+#[wasm_bindgen]
+impl PaymentAddress {
+    /// Decode a 43-byte diversifier || pk_d encoding
+    pub fn from_bytes(bytes: &[u8]) -> Result<PaymentAddress, OmniError> {
+        if bytes.len() != 43 {
+            return Err(OmniError::InvalidLength("Payment address must be 43 bytes".into()));
+        }
+        PaymentAddress::from_parts(&bytes[0..11], &bytes[11..43])
+    }
+
+    /// Build a payment address from its components
+    pub fn from_parts(diversifier: &[u8], pk_d: &[u8]) -> Result<PaymentAddress, OmniError> {
+        if diversifier.len() != 11 {
+            return Err(OmniError::InvalidLength("Diversifier must be 11 bytes".into()));
+        }
+        if pk_d.len() != 32 {
+            return Err(OmniError::InvalidLength("pk_d must be 32 bytes".into()));
+        }
+
+        let mut address = PaymentAddress {
+            diversifier: [0u8; 11],
+            pk_d: [0u8; 32],
+        };
+        address.diversifier.copy_from_slice(diversifier);
+        address.pk_d.copy_from_slice(pk_d);
+
+        diversifier_to_point(&address.diversifier)?;
+        parse_non_identity_point(pk_d, "Invalid transmission key")?;
+
+        Ok(address)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [&self.diversifier[..], &self.pk_d[..]].concat()
+    }
+
+    /// A plain object with hex-encoded fields, for persisting as JSON
+    pub fn to_json(&self) -> Result<JsValue, OmniError> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| OmniError::EncodingFailed(e.to_string()))
+    }
+
+    /// Parse the object produced by `to_json`
+    pub fn from_json(value: JsValue) -> Result<PaymentAddress, OmniError> {
+        serde_wasm_bindgen::from_value(value).map_err(|e| OmniError::InvalidEncoding(e.to_string()))
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn diversifier(&self) -> Vec<u8> {
+        self.diversifier.to_vec()
+    }
+
+    /// The diversified transmission key
+    #[wasm_bindgen(getter)]
+    pub fn pk_d(&self) -> Vec<u8> {
+        self.pk_d.to_vec()
+    }
+
+    /// Bech32 encoding for the given network
+    pub fn encode(&self, network: Network) -> Result<String, OmniError> {
+        encode_payment_address(&self.to_bytes(), network)
+    }
+}
+
+impl PaymentAddress {
+    /// pk_d = [ivk] g_d at the first diversifier index >= `diversifier_index`
+    /// whose FF1-AES256_dk diversifier is valid, along with the index actually used
+    pub(crate) fn from_ivk(
+        ivk: &Fr,
+        dk: &[u8],
+        diversifier_index: u32,
+    ) -> Result<(PaymentAddress, u32), OmniError> {
+        let (diversifier, index) = find_ff1_diversifier(dk, diversifier_index.into())?;
+        let index = u32::try_from(index)
+            .map_err(|_| OmniError::InvalidIndex("No valid diversifier index below 2^32".into()))?;
+        Ok((PaymentAddress::from_diversifier(ivk, diversifier)?, index))
+    }
+
+    /// pk_d = [ivk] g_d for a given diversifier. Only the public diversifier's
+    /// validity is branched on; the multiplication by ivk is constant time.
+    pub(crate) fn from_diversifier(ivk: &Fr, diversifier: [u8; 11]) -> Result<PaymentAddress, OmniError> {
+        let g_d = diversifier_to_point(&diversifier)?;
+
+        Ok(PaymentAddress {
+            diversifier,
+            pk_d: (g_d * ivk).to_bytes(),
+        })
+    }
+}
+
+/// Hex-encoded field representation shared by the serde impls
+#[derive(Serialize, Deserialize)]
+struct SpendingKeyJson {
+    ask: String,
+    nsk: String,
+    ovk: String,
+}
+
+impl From<SpendingKey> for SpendingKeyJson {
+    fn from(key: SpendingKey) -> Self {
+        SpendingKeyJson {
+            ask: hex_encode(&key.ask),
+            nsk: hex_encode(&key.nsk),
+            ovk: hex_encode(&key.ovk),
+        }
+    }
+}
+
+impl TryFrom<SpendingKeyJson> for SpendingKey {
+    type Error = OmniError;
+
+    fn try_from(json: SpendingKeyJson) -> Result<Self, Self::Error> {
+        SpendingKey::from_parts(&hex_decode(&json.ask)?, &hex_decode(&json.nsk)?, &hex_decode(&json.ovk)?)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct FullViewingKeyJson {
+    ak: String,
+    nk: String,
+    ovk: String,
+    dk: String,
+}
+
+impl From<FullViewingKey> for FullViewingKeyJson {
+    fn from(key: FullViewingKey) -> Self {
+        FullViewingKeyJson {
+            ak: hex_encode(&key.ak),
+            nk: hex_encode(&key.nk),
+            ovk: hex_encode(&key.ovk),
+            dk: hex_encode(&key.dk),
+        }
+    }
+}
+
+impl TryFrom<FullViewingKeyJson> for FullViewingKey {
+    type Error = OmniError;
+
+    fn try_from(json: FullViewingKeyJson) -> Result<Self, Self::Error> {
+        FullViewingKey::from_parts(
+            &hex_decode(&json.ak)?,
+            &hex_decode(&json.nk)?,
+            &hex_decode(&json.ovk)?,
+            &hex_decode(&json.dk)?,
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct PaymentAddressJson {
+    diversifier: String,
+    pk_d: String,
+}
+
+impl From<PaymentAddress> for PaymentAddressJson {
+    fn from(address: PaymentAddress) -> Self {
+        PaymentAddressJson {
+            diversifier: hex_encode(&address.diversifier),
+            pk_d: hex_encode(&address.pk_d),
+        }
+    }
+}
+
+impl TryFrom<PaymentAddressJson> for PaymentAddress {
+    type Error = OmniError;
+
+    fn try_from(json: PaymentAddressJson) -> Result<Self, Self::Error> {
+        PaymentAddress::from_parts(&hex_decode(&json.diversifier)?, &hex_decode(&json.pk_d)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{derive_default_address, derive_payment_address, unified};
+
+    /// UFVK of account 0 of the all-zero seed under coin type 0, from the
+    /// librustzcash test suite
+    const UFVK: &str = "uview12z384wdq76ceewlsu0esk7d97qnd23v2qnvhujxtcf2lsq8g4hwzpx44fwxssnm5tg8skyh4tnc8gydwxefnnm0hd0a6c6etmj0pp9jqkdsllkr70u8gpf7ndsfqcjlqn6dec3faumzqlqcmtjf8vp92h7kj38ph2786zx30hq2wru8ae3excdwc8w0z3t9fuw7mt7xy5sn6s4e45kwm0cjp70wytnensgdnev286t3vew3yuwt2hcz865y037k30e428dvgne37xvyeal2vu8yjnznphf9t2rw3gdp0hk5zwq00ws8f3l3j5n3qkqgsyzrwx4qzmgq0xwwk4vz2r6vtsykgz089jncvycmem3535zjwvvtvjw8v98y0d5ydwte575gjm7a7k";
+
+    #[test]
+    fn default_address_matches_free_functions() {
+        let account = zip32::derive_account_keys(&[0u8; 32], 0, Network::Mainnet).unwrap();
+        let dfvk = account.extended_spending_key().to_diversifiable_fvk().unwrap();
+        let fvk = account.full_viewing_key().unwrap();
+        assert_eq!(fvk.to_bytes(), dfvk);
+
+        let address = fvk.default_address(Network::Mainnet).unwrap();
+        assert_eq!(address, derive_default_address(&dfvk, Network::Mainnet).unwrap());
+        assert_eq!(address, account.address(0).unwrap().encode(Network::Mainnet).unwrap());
+
+        let dfvk = zip32::derive_master(&[0u8; 32])
+            .and_then(|m| m.derive_child(32))
+            .and_then(|k| k.derive_child(0))
+            .and_then(|k| k.derive_child(0))
+            .and_then(|k| k.to_diversifiable_fvk())
+            .unwrap();
+        let fvk = FullViewingKey::from_bytes(&dfvk).unwrap();
+
+        let ua = unified::unified_addresses_from_ufvk(UFVK, 0, Network::Mainnet).unwrap();
+        let receivers = unified::decode_items(&ua, Network::Mainnet).unwrap();
+        let sapling = receivers.iter().find(|(typecode, _)| *typecode == 2).unwrap();
+        assert_eq!(sapling.1, fvk.payment_address(0).unwrap().0.to_bytes());
+
+        for index in [1, 7, 100] {
+            let address = fvk.address(index, Network::Mainnet).unwrap();
+            assert_eq!(address, derive_payment_address(&dfvk, index, Network::Mainnet).unwrap());
+        }
+    }
+
+    #[test]
+    fn constructors_reject_wrong_lengths() {
+        let sk = SpendingKey::from_seed(&(0u8..32).collect::<Vec<_>>()).unwrap();
+        let fvk = sk.to_full_viewing_key().unwrap();
+        let (address, _) = fvk.payment_address(0).unwrap();
+        let short = |result: Result<(), OmniError>| matches!(result, Err(OmniError::InvalidLength(_)));
+
+        let (ask, nsk, ovk) = (sk.ask(), sk.nsk(), sk.ovk());
+        assert!(SpendingKey::from_parts(&ask, &nsk, &ovk).is_ok());
+        assert!(short(SpendingKey::from_bytes(&sk.to_bytes()[..95]).map(drop)));
+        assert!(short(SpendingKey::from_parts(&ask[..31], &nsk, &ovk).map(drop)));
+        assert!(short(SpendingKey::from_parts(&ask, &[nsk.clone(), vec![0]].concat(), &ovk).map(drop)));
+        assert!(short(SpendingKey::from_parts(&ask, &nsk, &ovk[..31]).map(drop)));
+
+        let (ak, nk, dk) = (fvk.ak(), fvk.nk(), fvk.dk());
+        assert!(FullViewingKey::from_parts(&ak, &nk, &ovk, &dk).is_ok());
+        assert!(short(FullViewingKey::from_bytes(&fvk.to_bytes()[..127]).map(drop)));
+        assert!(short(FullViewingKey::from_bytes(&fvk.to_bytes()[..96]).map(drop)));
+        assert!(short(FullViewingKey::from_parts(&ak[..31], &nk, &ovk, &dk).map(drop)));
+        assert!(short(FullViewingKey::from_parts(&ak, &nk[..31], &ovk, &dk).map(drop)));
+        assert!(short(FullViewingKey::from_parts(&ak, &nk, &ovk[..31], &dk).map(drop)));
+        assert!(short(FullViewingKey::from_parts(&ak, &nk, &ovk, &dk[..31]).map(drop)));
+
+        let (d, pk_d) = (address.diversifier(), address.pk_d());
+        assert!(PaymentAddress::from_parts(&d, &pk_d).is_ok());
+        assert!(short(PaymentAddress::from_bytes(&address.to_bytes()[..42]).map(drop)));
+        assert!(short(PaymentAddress::from_parts(&d[..10], &pk_d).map(drop)));
+        assert!(short(PaymentAddress::from_parts(&d, &pk_d[..31]).map(drop)));
+    }
+
+    #[test]
+    fn json_round_trips_with_hex_fields() {
+        let sk = SpendingKey::from_seed(&(0u8..32).collect::<Vec<_>>()).unwrap();
+        let fvk = sk.to_full_viewing_key().unwrap();
+        let (address, _) = fvk.payment_address(0).unwrap();
+
+        let json = serde_json::to_value(&sk).unwrap();
+        assert_eq!(json["ask"], hex_encode(&sk.ask()));
+        let decoded: SpendingKey = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded.to_bytes(), sk.to_bytes());
+
+        let json = serde_json::to_string(&fvk).unwrap();
+        let decoded: FullViewingKey = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.to_bytes(), fvk.to_bytes());
+        assert_eq!(serde_json::to_value(&fvk).unwrap()["dk"], hex_encode(&fvk.dk()));
+
+        let json = serde_json::to_value(&address).unwrap();
+        assert_eq!(json["diversifier"], hex_encode(&address.diversifier()));
+        let decoded: PaymentAddress = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded.to_bytes(), address.to_bytes());
+
+        // Deserializing runs the same checks as the constructors
+        let mut json = serde_json::to_value(&fvk).unwrap();
+        json["nk"] = "00".repeat(31).into();
+        assert!(serde_json::from_value::<FullViewingKey>(json).is_err());
+    }
+
+    #[test]
+    fn spending_key_fvk_matches_diversifiable_fvk() {
+        let sk = SpendingKey::from_seed(&(0u8..32).collect::<Vec<_>>()).unwrap();
+        let dfvk = crate::derive_diversifiable_fvk(&sk.to_bytes()).unwrap();
+        let fvk = sk.to_full_viewing_key().unwrap();
+
+        assert_eq!(fvk.to_bytes(), dfvk);
+        assert_eq!(FullViewingKey::from_bytes(&dfvk).unwrap().to_bytes(), dfvk);
+        assert_eq!(
+            fvk.address(5, Network::Testnet).unwrap(),
+            derive_payment_address(&dfvk, 5, Network::Testnet).unwrap()
+        );
+
+        // derive_viewing_key's ak || nk || ivk || ovk is the same length but not accepted
+        let viewing_key = crate::derive_viewing_key(&sk.to_bytes()).unwrap();
+        assert!(matches!(FullViewingKey::from_bytes(&viewing_key), Err(OmniError::InvalidEncoding(_))));
+    }
+}
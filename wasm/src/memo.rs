@@ -0,0 +1,112 @@
+//! ZIP-302 memo field encoding
+
+use wasm_bindgen::prelude::*;
+
+use crate::OmniError;
+
+/// Size of the memo field in a Sapling note plaintext
+pub const MEMO_SIZE: usize = 512;
+
+/// First byte of the "no memo" sentinel; the remaining bytes are zero
+const NO_MEMO: u8 = 0xF6;
+
+/// Text memos start with a byte no greater than this
+const MAX_TEXT_LEAD_BYTE: u8 = 0xF4;
+
+/// Encode a UTF-8 text memo, zero-padded to 512 bytes. An empty string
+/// encodes as the "no memo" sentinel.
+#[wasm_bindgen]
+pub fn encode_memo(text: &str) -> Result<Vec<u8>, OmniError> {
+    let bytes = text.as_bytes();
+    if bytes.len() > MEMO_SIZE {
+        return Err(OmniError::InvalidLength("Memo text must be at most 512 bytes".into()));
+    }
+
+    let mut memo = vec![0u8; MEMO_SIZE];
+    if bytes.is_empty() {
+        memo[0] = NO_MEMO;
+    } else {
+        memo[..bytes.len()].copy_from_slice(bytes);
+    }
+    Ok(memo)
+}
+
+/// Decode a 512-byte memo field, returning `None` for the "no memo" sentinel
+#[wasm_bindgen]
+pub fn decode_memo(bytes: &[u8]) -> Result<Option<String>, OmniError> {
+    if bytes.len() != MEMO_SIZE {
+        return Err(OmniError::InvalidLength("Memo must be 512 bytes".into()));
+    }
+
+    if bytes[0] == NO_MEMO && bytes[1..].iter().all(|&b| b == 0) {
+        return Ok(None);
+    }
+    if bytes[0] > MAX_TEXT_LEAD_BYTE {
+        return Err(OmniError::InvalidEncoding("Memo is not a text memo".into()));
+    }
+
+    // Trailing zero padding is not part of the text
+    let end = bytes.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+    String::from_utf8(bytes[..end].to_vec())
+        .map(Some)
+        .map_err(|_| OmniError::InvalidEncoding("Memo text is not valid UTF-8".into()))
+}
+
+/// A memo's text with trailing zero padding removed, decoded lossily so binary
+/// memos never fail, and whether it is a valid UTF-8 text memo. The "no memo"
+/// sentinel yields empty text.
+pub(crate) fn memo_text(memo: &[u8]) -> (String, bool) {
+    if memo[0] == NO_MEMO && memo[1..].iter().all(|&b| b == 0) {
+        return (String::new(), false);
+    }
+
+    let end = memo.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+    let utf8 = memo[0] <= MAX_TEXT_LEAD_BYTE && std::str::from_utf8(&memo[..end]).is_ok();
+    (String::from_utf8_lossy(&memo[..end]).into_owned(), utf8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_memo_sentinel_and_length_limit() {
+        let empty = encode_memo("").unwrap();
+        assert_eq!(empty[0], NO_MEMO);
+        assert!(empty[1..].iter().all(|&b| b == 0));
+        assert_eq!(decode_memo(&empty).unwrap(), None);
+
+        let memo = encode_memo("thanks for the coffee ☕").unwrap();
+        assert_eq!(memo.len(), MEMO_SIZE);
+        assert_eq!(decode_memo(&memo).unwrap().as_deref(), Some("thanks for the coffee ☕"));
+
+        let longest = "z".repeat(MEMO_SIZE);
+        assert_eq!(decode_memo(&encode_memo(&longest).unwrap()).unwrap(), Some(longest));
+        assert!(matches!(encode_memo(&"z".repeat(MEMO_SIZE + 1)), Err(OmniError::InvalidLength(_))));
+        // 171 three-byte characters are 513 bytes
+        assert!(matches!(encode_memo(&"€".repeat(171)), Err(OmniError::InvalidLength(_))));
+
+        assert!(matches!(decode_memo(&memo[..511]), Err(OmniError::InvalidLength(_))));
+    }
+
+    #[test]
+    fn binary_memos_decode_lossily_without_failing() {
+        let text = encode_memo("hi").unwrap();
+        assert_eq!(memo_text(&text), ("hi".to_string(), true));
+        assert_eq!(memo_text(&encode_memo("").unwrap()), (String::new(), false));
+
+        // A ZIP-302 arbitrary-data memo: 0xFF then bytes that are not UTF-8
+        let mut binary = [0u8; MEMO_SIZE];
+        binary[..5].copy_from_slice(&[0xff, 0x00, 0xc3, 0x28, 0x80]);
+        let (decoded, utf8) = memo_text(&binary);
+        assert!(!utf8);
+        assert_eq!(decoded, "\u{fffd}\0\u{fffd}(\u{fffd}");
+        assert!(matches!(decode_memo(&binary), Err(OmniError::InvalidEncoding(_))));
+
+        // A text lead byte followed by an invalid sequence is still not text
+        let mut broken = [0u8; MEMO_SIZE];
+        broken[..4].copy_from_slice(&[b'o', b'k', 0xc3, 0x28]);
+        assert_eq!(memo_text(&broken), ("ok\u{fffd}(".to_string(), false));
+        assert!(matches!(decode_memo(&broken), Err(OmniError::InvalidEncoding(_))));
+    }
+}
@@ -0,0 +1,477 @@
+//! Sapling note commitment tree (Zcash protocol spec §4.9)
+
+use std::sync::OnceLock;
+
+use js_sys::{Array, Uint8Array};
+use jubjub::Base;
+use wasm_bindgen::prelude::*;
+
+use crate::pedersen::{bytes_to_bits_le, extract_u, pedersen_hash_to_point};
+use crate::OmniError;
+
+/// Depth of the Sapling note commitment tree
+pub const SAPLING_TREE_DEPTH: usize = 32;
+
+/// MerkleCRH^Sapling hashes the low 255 bits of each child
+const NODE_BITS: usize = 255;
+
+/// An append-only Sapling note commitment tree, stored as its frontier: the
+/// latest leaf pair and one completed left subtree per level above it
+/// (incrementalmerkletree's `CommitmentTree`)
+#[wasm_bindgen]
+#[derive(Clone, Default)]
+pub struct SaplingMerkleTree {
+    left: Option<[u8; 32]>,
+    right: Option<[u8; 32]>,
+    /// Completed left subtrees awaiting a right sibling; `parents[i]` is a
+    /// node at level i + 1
+    parents: Vec<Option<[u8; 32]>>,
+}
+
+#[wasm_bindgen]
+impl SaplingMerkleTree {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> SaplingMerkleTree {
+        SaplingMerkleTree::default()
+    }
+
+    /// Number of commitments appended so far
+    #[wasm_bindgen(getter)]
+    pub fn size(&self) -> u64 {
+        let leaves = u64::from(self.left.is_some()) + u64::from(self.right.is_some());
+        self.parents
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.is_some())
+            .fold(leaves, |size, (i, _)| size + (1 << (i + 1)))
+    }
+
+    /// Append a note commitment u-coordinate as the next leaf
+    pub fn append(&mut self, cmu: &[u8]) -> Result<(), OmniError> {
+        if cmu.len() != 32 {
+            return Err(OmniError::InvalidLength("Note commitment must be 32 bytes".into()));
+        }
+        if self.size() >= 1 << SAPLING_TREE_DEPTH {
+            return Err(OmniError::TreeFull("Note commitment tree is full".into()));
+        }
+
+        let mut leaf = [0u8; 32];
+        leaf.copy_from_slice(cmu);
+        if bool::from(Base::from_bytes(&leaf).is_none()) {
+            return Err(OmniError::InvalidEncoding("Note commitment is not a canonical field element".into()));
+        }
+
+        let (Some(lhs), Some(rhs)) = (self.left, self.right) else {
+            match self.left {
+                None => self.left = Some(leaf),
+                Some(_) => self.right = Some(leaf),
+            }
+            return Ok(());
+        };
+
+        // Both leaves are full: start a new pair and carry their parent up
+        // through the completed subtrees like a binary counter
+        self.left = Some(leaf);
+        self.right = None;
+        let mut node = merkle_crh(0, &lhs, &rhs);
+        for (i, parent) in self.parents.iter_mut().enumerate() {
+            match parent.take() {
+                Some(lhs) => node = merkle_crh(i + 1, &lhs, &node),
+                None => {
+                    *parent = Some(node);
+                    return Ok(());
+                }
+            }
+        }
+        self.parents.push(Some(node));
+        Ok(())
+    }
+
+    /// The current anchor
+    pub fn root(&self) -> Vec<u8> {
+        let Some(left) = self.left else {
+            return empty_roots()[SAPLING_TREE_DEPTH].to_vec();
+        };
+
+        let mut node = merkle_crh(0, &left, &self.right.unwrap_or(empty_roots()[0]));
+        for level in 1..SAPLING_TREE_DEPTH {
+            node = match self.parents.get(level - 1).copied().flatten() {
+                Some(lhs) => merkle_crh(level, &lhs, &node),
+                None => merkle_crh(level, &node, &empty_roots()[level]),
+            };
+        }
+        node.to_vec()
+    }
+
+    /// The authentication path for the most recently appended leaf: 32
+    /// sibling nodes, leaf level first. Use `IncrementalWitness` to keep a
+    /// path current as later leaves are appended.
+    pub fn witness(&self) -> Result<Vec<u8>, OmniError> {
+        let left = self
+            .left
+            .ok_or_else(|| OmniError::InvalidIndex("Cannot witness an empty tree".into()))?;
+
+        let mut path = Vec::with_capacity(SAPLING_TREE_DEPTH * 32);
+        path.extend_from_slice(match self.right {
+            Some(_) => &left,
+            None => &empty_roots()[0],
+        });
+        for level in 1..SAPLING_TREE_DEPTH {
+            let sibling = self.parents.get(level - 1).copied().flatten();
+            path.extend_from_slice(&sibling.unwrap_or(empty_roots()[level]));
+        }
+
+        Ok(path)
+    }
+}
+
+impl SaplingMerkleTree {
+    /// The most recently appended leaf
+    fn latest_leaf(&self) -> Option<[u8; 32]> {
+        self.right.or(self.left)
+    }
+}
+
+/// The anchor of a fresh tree holding `commitments`, an array of 32-byte cmu
+/// `Uint8Array`s, in order
+#[wasm_bindgen]
+pub fn sapling_anchor(commitments: JsValue) -> Result<Vec<u8>, OmniError> {
+    if !Array::is_array(&commitments) {
+        return Err(OmniError::InvalidEncoding("Commitments must be an array".into()));
+    }
+
+    let mut cmus = Vec::new();
+    for cmu in Array::from(&commitments).iter() {
+        if !cmu.is_instance_of::<Uint8Array>() {
+            return Err(OmniError::InvalidEncoding("Commitments must be Uint8Arrays".into()));
+        }
+        cmus.push(Uint8Array::new(&cmu).to_vec());
+    }
+    anchor(&cmus)
+}
+
+/// The root behind `sapling_anchor`
+fn anchor(commitments: &[Vec<u8>]) -> Result<Vec<u8>, OmniError> {
+    let mut tree = SaplingMerkleTree::new();
+    for cmu in commitments {
+        tree.append(cmu)?;
+    }
+    Ok(tree.root())
+}
+
+/// An authentication path for one leaf that is kept current as later
+/// commitments are appended, without rehashing the whole tree
+/// (incrementalmerkletree's `IncrementalWitness`)
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct IncrementalWitness {
+    position: u64,
+    leaf: [u8; 32],
+    /// Complete subtrees to the left of the leaf, lowest level first
+    left: Vec<[u8; 32]>,
+    /// Right siblings completed since the witness was taken, lowest level first
+    filled: Vec<[u8; 32]>,
+    /// Completed subtrees of the right sibling currently being built, indexed
+    /// by height
+    cursor: Vec<Option<[u8; 32]>>,
+}
+
+#[wasm_bindgen]
+impl IncrementalWitness {
+    /// Witness the most recently appended leaf of `tree`
+    pub fn from_tree(tree: &SaplingMerkleTree) -> Result<IncrementalWitness, OmniError> {
+        let leaf = tree
+            .latest_leaf()
+            .ok_or_else(|| OmniError::InvalidIndex("Cannot witness an empty tree".into()))?;
+        let position = tree.size() - 1;
+        let path = tree.witness()?;
+
+        let left = (0..SAPLING_TREE_DEPTH)
+            .filter(|&level| (position >> level) & 1 == 1)
+            .map(|level| {
+                let mut node = [0u8; 32];
+                node.copy_from_slice(&path[level * 32..(level + 1) * 32]);
+                node
+            })
+            .collect();
+
+        Ok(IncrementalWitness {
+            position,
+            leaf,
+            left,
+            filled: Vec::new(),
+            cursor: Vec::new(),
+        })
+    }
+
+    /// Position of the witnessed leaf
+    #[wasm_bindgen(getter)]
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Account for the next note commitment appended to the tree
+    pub fn append(&mut self, cmu: &[u8]) -> Result<(), OmniError> {
+        if cmu.len() != 32 {
+            return Err(OmniError::InvalidLength("Note commitment must be 32 bytes".into()));
+        }
+
+        let mut node = [0u8; 32];
+        node.copy_from_slice(cmu);
+        if bool::from(Base::from_bytes(&node).is_none()) {
+            return Err(OmniError::InvalidEncoding("Note commitment is not a canonical field element".into()));
+        }
+
+        let target = self
+            .next_right_level()
+            .ok_or_else(|| OmniError::TreeFull("Note commitment tree is full".into()))?;
+        self.cursor.resize(target, None);
+
+        // Carry the new leaf up through the cursor like a binary counter
+        for level in 0..target {
+            match self.cursor[level].take() {
+                Some(lhs) => node = merkle_crh(level, &lhs, &node),
+                None => {
+                    self.cursor[level] = Some(node);
+                    return Ok(());
+                }
+            }
+        }
+
+        self.filled.push(node);
+        self.cursor.clear();
+        Ok(())
+    }
+
+    /// The current authentication path: 32 sibling nodes, leaf level first,
+    /// in the same layout as `SaplingMerkleTree.witness`
+    pub fn path(&self) -> Vec<u8> {
+        let building = self.next_right_level();
+        let mut left = self.left.iter();
+        let mut filled = self.filled.iter();
+        let mut path = Vec::with_capacity(SAPLING_TREE_DEPTH * 32);
+
+        for level in 0..SAPLING_TREE_DEPTH {
+            let sibling = if (self.position >> level) & 1 == 1 {
+                *left.next().expect("a left sibling for every set position bit")
+            } else if let Some(node) = filled.next() {
+                *node
+            } else if building == Some(level) {
+                self.cursor_root(level)
+            } else {
+                empty_roots()[level]
+            };
+            path.extend_from_slice(&sibling);
+        }
+
+        path
+    }
+
+    /// The anchor of the tree this witness is current with
+    pub fn root(&self) -> Vec<u8> {
+        let path = self.path();
+        let mut node = self.leaf;
+        for level in 0..SAPLING_TREE_DEPTH {
+            let mut sibling = [0u8; 32];
+            sibling.copy_from_slice(&path[level * 32..(level + 1) * 32]);
+            node = if (self.position >> level) & 1 == 1 {
+                merkle_crh(level, &sibling, &node)
+            } else {
+                merkle_crh(level, &node, &sibling)
+            };
+        }
+        node.to_vec()
+    }
+}
+
+impl IncrementalWitness {
+    /// The level of the right sibling that the next appended leaf belongs to
+    fn next_right_level(&self) -> Option<usize> {
+        (0..SAPLING_TREE_DEPTH)
+            .filter(|&level| (self.position >> level) & 1 == 0)
+            .nth(self.filled.len())
+    }
+
+    /// Root of the partially built right sibling at `level`, padded with
+    /// empty subtrees
+    fn cursor_root(&self, level: usize) -> [u8; 32] {
+        let mut node: Option<[u8; 32]> = None;
+        for height in 0..level {
+            node = match (self.cursor.get(height).copied().flatten(), node) {
+                (Some(lhs), rhs) => Some(merkle_crh(height, &lhs, &rhs.unwrap_or(empty_roots()[height]))),
+                (None, Some(lhs)) => Some(merkle_crh(height, &lhs, &empty_roots()[height])),
+                (None, None) => None,
+            };
+        }
+        node.unwrap_or(empty_roots()[level])
+    }
+}
+
+/// MerkleCRH^Sapling(layer, left, right), with the layer as a 6-bit personalization
+pub(crate) fn merkle_crh(level: usize, lhs: &[u8; 32], rhs: &[u8; 32]) -> [u8; 32] {
+    let personalization: Vec<bool> = (0..6).map(|i| (level >> i) & 1 == 1).collect();
+    let bits = node_bits(lhs).chain(node_bits(rhs));
+
+    extract_u(&pedersen_hash_to_point(&personalization, bits))
+}
+
+/// Roots of empty subtrees, from the uncommitted leaf (1) up to the full tree
+pub(crate) fn empty_roots() -> &'static [[u8; 32]] {
+    static EMPTY_ROOTS: OnceLock<Vec<[u8; 32]>> = OnceLock::new();
+    EMPTY_ROOTS.get_or_init(|| {
+        let mut roots = vec![Base::one().to_bytes()];
+        for level in 0..SAPLING_TREE_DEPTH {
+            let next = merkle_crh(level, &roots[level], &roots[level]);
+            roots.push(next);
+        }
+        roots
+    })
+}
+
+fn node_bits(node: &[u8; 32]) -> impl Iterator<Item = bool> + '_ {
+    bytes_to_bits_le(node).take(NODE_BITS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex_decode;
+
+    /// The first commitments of zcashd's merkle_commitments_sapling.json, byte-reversed
+    const COMMITMENTS: [&str; 8] = [
+        "b02310f2e087e55bfd07ef5e242e3b87ee5d00c9ab52f61e6bd42542f93a6f55",
+        "225747f3b5d5dab4e5a424f81f85c904ff43286e0f3fd07ef0b8c6a627b11458",
+        "7c3ea01a6e3a3d90cf59cd789e467044b5cd78eb2c84cc6816f960746d0e036c",
+        "50421d6c2c94571dfaaa135a4ff15bf916681ebd62c0e43e69e3b90684d0a030",
+        "aaec63863aaa0b2e3b8009429bdddd455e59be6f40ccab887a32eb98723efc12",
+        "f76748d40d5ee5f9a608512e7954dd515f86e8f6d009141c89163de1cf351a02",
+        "bc8a5ec71647415c380203b681f7717366f3501661512225b6dc3e121efc0b2e",
+        "da1adda2ccde9381e11151686c121e7f52d19a990439161c7eb5a9f94be5a511",
+    ];
+
+    /// zcashd's merkle_roots_sapling.json roots of a depth-4 tree after each
+    /// of the commitments above
+    const DEPTH_4_ROOTS: [&str; 8] = [
+        "8c3daa300c9710bf24d2595536e7c80ff8d147faca726636d28e8683a0c27703",
+        "8611f17378eb55e8c3c3f0a5f002e2b0a7ca39442fc928322b8072d1079c213d",
+        "3db73b998d536be0e1c2ec124df8e0f383ae7b602968ff6a5276ca0695023c46",
+        "7ac2e6442fec5970e116dfa4f2ee606f395366cafb1fa7dfd6c3de3ce18c4363",
+        "6a8f11ab2a11c262e39ed4ea3825ae6c94739ccf94479cb69402c5722b034532",
+        "149595eed0b54a7e694cc8a68372525b9ae2c7b102514f527460db91eb690565",
+        "8c0432f1994a2381a7a4b5fda770336011f9e0b30784f9a5597901619c797045",
+        "e780c48d70420601f3313ff8488d7766b70c059c53aa3cda2ff1ef57ff62383c",
+    ];
+
+    fn node(hex: &str) -> [u8; 32] {
+        hex_decode(hex).unwrap().try_into().unwrap()
+    }
+
+    /// Fold an authentication path from `leaf` at `position` up to the root
+    fn root_from_path(leaf: [u8; 32], position: u64, path: &[u8]) -> Vec<u8> {
+        let mut node = leaf;
+        for (level, sibling) in path.chunks(32).enumerate() {
+            let sibling: [u8; 32] = sibling.try_into().unwrap();
+            node = if (position >> level) & 1 == 1 {
+                merkle_crh(level, &sibling, &node)
+            } else {
+                merkle_crh(level, &node, &sibling)
+            };
+        }
+        node.to_vec()
+    }
+
+    #[test]
+    fn root_matches_zcashd_vectors() {
+        let mut tree = SaplingMerkleTree::new();
+        for (size, (cmu, depth_4_root)) in COMMITMENTS.iter().zip(DEPTH_4_ROOTS).enumerate() {
+            tree.append(&node(cmu)).unwrap();
+            assert_eq!(tree.size(), size as u64 + 1);
+
+            // With at most 16 leaves, every node above level 4 has an empty right child
+            let mut root = node(depth_4_root);
+            for level in 4..SAPLING_TREE_DEPTH {
+                root = merkle_crh(level, &root, &empty_roots()[level]);
+            }
+            assert_eq!(tree.root(), root.to_vec());
+        }
+    }
+
+    #[test]
+    fn witness_authenticates_latest_leaf() {
+        let mut tree = SaplingMerkleTree::new();
+        assert!(tree.witness().is_err());
+
+        for (position, cmu) in COMMITMENTS.iter().enumerate() {
+            tree.append(&node(cmu)).unwrap();
+            let path = tree.witness().unwrap();
+            assert_eq!(path.len(), SAPLING_TREE_DEPTH * 32);
+            assert_eq!(root_from_path(node(cmu), position as u64, &path), tree.root());
+        }
+    }
+
+    #[test]
+    fn empty_tree_root_is_empty_root() {
+        assert_eq!(SaplingMerkleTree::new().root(), empty_roots()[SAPLING_TREE_DEPTH].to_vec());
+    }
+
+    /// Root of the height-`level` subtree at `index` over `leaves`, padded with empty leaves
+    fn subtree_root(leaves: &[[u8; 32]], level: usize, index: u64) -> [u8; 32] {
+        if index << level >= leaves.len() as u64 {
+            return empty_roots()[level];
+        }
+        if level == 0 {
+            return leaves[index as usize];
+        }
+        let lhs = subtree_root(leaves, level - 1, index * 2);
+        let rhs = subtree_root(leaves, level - 1, index * 2 + 1);
+        merkle_crh(level - 1, &lhs, &rhs)
+    }
+
+    #[test]
+    fn incremental_witness_matches_full_tree() {
+        let leaves: Vec<[u8; 32]> = COMMITMENTS.iter().map(|cmu| node(cmu)).collect();
+
+        for position in 0..leaves.len() {
+            let mut tree = SaplingMerkleTree::new();
+            for leaf in &leaves[..=position] {
+                tree.append(leaf).unwrap();
+            }
+            let mut witness = IncrementalWitness::from_tree(&tree).unwrap();
+            assert_eq!(witness.position(), position as u64);
+
+            for (size, leaf) in leaves.iter().enumerate().skip(position + 1) {
+                tree.append(leaf).unwrap();
+                witness.append(leaf).unwrap();
+
+                // The path a full tree of the same leaves gives for `position`
+                let expected: Vec<u8> = (0..SAPLING_TREE_DEPTH)
+                    .flat_map(|level| subtree_root(&leaves[..=size], level, (position as u64 >> level) ^ 1))
+                    .collect();
+                assert_eq!(witness.path(), expected);
+                assert_eq!(witness.root(), tree.root());
+                assert_eq!(root_from_path(leaves[position], position as u64, &witness.path()), tree.root());
+            }
+        }
+    }
+
+    #[test]
+    fn one_leaf_anchor_hashes_up_with_empty_siblings() {
+        let leaf = node(COMMITMENTS[0]);
+        let mut expected = leaf;
+        for level in 0..SAPLING_TREE_DEPTH {
+            expected = merkle_crh(level, &expected, &empty_roots()[level]);
+        }
+        assert_eq!(anchor(&[leaf.to_vec()]).unwrap(), expected.to_vec());
+
+        // The same root extends zcashd's depth-4 root after one commitment
+        let mut root = node(DEPTH_4_ROOTS[0]);
+        for level in 4..SAPLING_TREE_DEPTH {
+            root = merkle_crh(level, &root, &empty_roots()[level]);
+        }
+        assert_eq!(expected, root);
+
+        assert_eq!(anchor(&[]).unwrap(), empty_roots()[SAPLING_TREE_DEPTH].to_vec());
+        assert!(matches!(anchor(&[leaf[..31].to_vec()]), Err(OmniError::InvalidLength(_))));
+        assert!(matches!(anchor(&[vec![0xff; 32]]), Err(OmniError::InvalidEncoding(_))));
+    }
+}
@@ -0,0 +1,198 @@
+//! Orchard key derivation (Zcash protocol spec §4.2.3, ZIP-32)
+
+use ff::{Field, FromUniformBytes, PrimeField};
+use group::{Curve, GroupEncoding};
+use pasta_curves::{arithmetic::CurveExt, pallas};
+use wasm_bindgen::prelude::*;
+
+use crate::{prf_expand_wide, Network, OmniError};
+
+#[cfg(feature = "orchard")]
+pub mod note;
+#[cfg(feature = "orchard")]
+mod poseidon;
+#[cfg(feature = "orchard")]
+mod sinsemilla;
+
+/// BLAKE2b personalization for the Orchard master key
+const ZIP32_ORCHARD_PERSONALIZATION: &[u8; 16] = b"ZcashIP32Orchard";
+
+/// Hash-to-curve domain for the Orchard fixed bases
+const ORCHARD_PERSONALIZATION: &str = "z.cash:Orchard";
+
+/// ZIP-32 purpose field, the first path element
+const ZIP32_PURPOSE: u32 = 32;
+
+/// Child indices with this bit set are hardened
+const HARDENED: u32 = 1 << 31;
+
+/// Derive the Orchard spending key at `m/32'/133'/account'` from a seed
+#[wasm_bindgen]
+pub fn orchard_spending_key(seed: &[u8], account: u32) -> Result<Vec<u8>, OmniError> {
+    if seed.len() < 32 || seed.len() > 252 {
+        return Err(OmniError::InvalidLength("Seed must be between 32 and 252 bytes".into()));
+    }
+    if account >= HARDENED {
+        return Err(OmniError::InvalidIndex("Account must be below 2^31".into()));
+    }
+
+    let mut key = master_key(seed)?;
+    for index in [ZIP32_PURPOSE, Network::Mainnet.coin_type(), account] {
+        key = derive_child(&key, index)?;
+    }
+
+    Ok(key[..32].to_vec())
+}
+
+/// Derive the 96-byte Orchard full viewing key ak || nk || rivk from a
+/// 32-byte spending key
+#[wasm_bindgen]
+pub fn orchard_full_viewing_key(sk: &[u8]) -> Result<Vec<u8>, OmniError> {
+    if sk.len() != 32 {
+        return Err(OmniError::InvalidLength("Orchard spending key must be 32 bytes".into()));
+    }
+
+    let ask = spend_authorizing_key(sk)?;
+    let nk = pallas::Base::from_uniform_bytes(&prf_expand_wide(sk, &[0x07]));
+    let rivk = to_scalar(sk, 0x08);
+
+    let mut fvk = Vec::with_capacity(96);
+    fvk.extend_from_slice(&spend_validating_key(&ask));
+    fvk.extend_from_slice(&nk.to_repr());
+    fvk.extend_from_slice(&rivk.to_repr());
+    Ok(fvk)
+}
+
+/// The master sk_m || c_m = BLAKE2b-512("ZcashIP32Orchard", seed)
+fn master_key(seed: &[u8]) -> Result<[u8; 64], OmniError> {
+    let i = blake2b_simd::Params::new()
+        .hash_length(64)
+        .personal(ZIP32_ORCHARD_PERSONALIZATION)
+        .hash(seed);
+    let mut key = [0u8; 64];
+    key.copy_from_slice(i.as_bytes());
+    spend_authorizing_key(&key[..32])?;
+    Ok(key)
+}
+
+/// Derive the hardened child sk_i || c_i of a 64-byte sk_par || c_par
+fn derive_child(parent: &[u8; 64], index: u32) -> Result<[u8; 64], OmniError> {
+    // I = PRF^expand(c_par, [0x81] || sk_par || I2LEOSP32(i))
+    let mut t = Vec::with_capacity(37);
+    t.push(0x81);
+    t.extend_from_slice(&parent[..32]);
+    t.extend_from_slice(&(index | HARDENED).to_le_bytes());
+
+    let child = prf_expand_wide(&parent[32..], &t);
+    spend_authorizing_key(&child[..32])?;
+    Ok(child)
+}
+
+/// ask = ToScalar^Orchard(PRF^expand(sk, [0x06])), which must be nonzero
+fn spend_authorizing_key(sk: &[u8]) -> Result<pallas::Scalar, OmniError> {
+    let ask = to_scalar(sk, 0x06);
+    if bool::from(ask.is_zero()) {
+        return Err(OmniError::DerivationFailed("Derived spend authorizing key is zero".into()));
+    }
+    Ok(ask)
+}
+
+/// ak = Extract_P([ask] G). The spec negates ask when [ask] G has ỹ = 1,
+/// which leaves the x-coordinate unchanged.
+fn spend_validating_key(ask: &pallas::Scalar) -> [u8; 32] {
+    let generator = pallas::Point::hash_to_curve(ORCHARD_PERSONALIZATION)(b"G");
+    let mut ak = (generator * ask).to_affine().to_bytes();
+
+    // Clear the ỹ sign bit to leave the x-coordinate
+    ak[31] &= 0x7F;
+    ak
+}
+
+/// ToScalar^Orchard(PRF^expand(sk, [tag]))
+fn to_scalar(sk: &[u8], tag: u8) -> pallas::Scalar {
+    pallas::Scalar::from_uniform_bytes(&prf_expand_wide(sk, &[tag]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::unified;
+
+    /// sk and ak || nk || rivk from the Orchard key component test vectors
+    const KEY_VECTORS: [(&str, &str); 10] = [
+        (
+            "5d7a8f739a2d9e945b0ce152a8049e294c4d6e66b164939daffa2ef6ee692148",
+            "740bbe5d0580b2cad430180d02cc128b9a140d5e07c151721dc16d25d4e20f159f2f826738945ad01f47f70db0c367c246c20c61ff5583948c39dea968fefd1b021ccf89604f5f7cc6e034b32d338908b819fbe325fee6458b56b4ca71a7e43d",
+        ),
+        (
+            "acd20b183e31d49f25c9a138f49b1a537edcf04be34a9851a7af9db6990ed83d",
+            "6de1349830d66d7b97fe231fc7b02ad64323629cfed1e3aa24ef052f56e4002aa8b73d979b6eaada8924bcbdc63a9ef4e87346f230aba6bbe1e2b43c5bea6b22dacb2f2a9ced363171821aaf5d8cd902bc5e3a5a41fb51ae61a9f02dc89d1d12",
+        ),
+        (
+            "b679f3dc601d008285edcbdae69ce8fc1be4aac00ff2711ebd931de518856878",
+            "efa5f1debeead0940a619ce0017bedb426657b2d07406664d895312ea1c3b33404514ea048b94363dea7cb3be8d62582ac52922e0865f662743b05eae8715f172a328f994f6e5ad29ca811ed344968ea2cfc3fd231030e37bbd56db42640231c",
+        ),
+        (
+            "731e985d99589c8bb838e8aaf745533ed9e8ae3a1cd074a51a20da8aba18d1db",
+            "b1e0acbc69bf377b85abf0f5a10be72c3b640006ff08505280e4f00fadf76328cf36ad6a066cd213e1d767ab071dc1167885c4168bc2e2175448563ad13f333dc41bbad35105a80314b79624b675241220b331f12592617bdb705bfcce72ae38",
+        ),
+        (
+            "5f2935395ee4762dd21afdbb5d47fa9a6dd984d567db2857b927b7fae2db5871",
+            "0d262de3609433fe5b7c862bc48ef56d832009f7242e1f7c770a12241dfa280751baf333cff1f2d0c7e3cff4d301299dc1efe98300314a541938029b45cc1521228feb79219873c7a7606e52973c85f460465a6059083919ed73eb805c118301",
+        ),
+        (
+            "1782fd2795d18a763624c25fa959cc97489ce75745824b77868c53239cfbdf73",
+            "d11787ca582f948e450718b36998df28bb0f1021ea843f867f8a170f5c33901f9e997d9d269787268e092a7c85417da530ea42fac668a749af55dfb71cdbbe09136c6fe2e2b79c5156db5047d8d5e795dfc0bdc0880853a44adb7392c02f941b",
+        ),
+        (
+            "6b95e3025b9792fff7f244fc716269b926d62e9596fa825c6bf21aff9e68625a",
+            "449a90d2e8d1a037642a97096c916543462a137ffea37baf41ef286bb732be2cfd3164c632bec94ce9fb2f302263b884abb9c10e55e448647f6798495c9d083fc0b36b56070fff2fdf38eba11a7424957195014cba43a56bd1b1658e66a39d00",
+        ),
+        (
+            "236c29af3923101756d9fa4bd0f7d2ddaacb6b0f86a2658e0a07a05ac5b95005",
+            "4efd5a2ef1ffa99a0ff62b767d44b3651ffa1c696915ac00a25ea3ac7dff990102ab995ce98f63025fb62428a0fbf52f2522e6a27261078a9f4d6a36a1c05d39d9840d0bd89520abbca7f10be6eba366f86ec3b78dbdf1ebfe20d99512af1515",
+        ),
+        (
+            "2d3825b3d6da0573d316eb160dc0b716c48fbd467f75b780149ae8808f4e68f5",
+            "762159a414f574b539750f22c8863b02d25cc10c9071fc0219e97f9392d0670c2591edf7ef4cf2184c34be93fcf612915042f15ab5084b14e166795b09cea133758fb250dd2950e5d2b2eed7ffcf94ae67cde125b95b479e2377813a85a03d2f",
+        ),
+        (
+            "4328b118c27402c70c3a90b49ad4bbc68e37c0aa7d9b3fe17799d73b841e7517",
+            "0d211a9060fbaa664e41a734ad1d8d4b025f8cc160e1f4e95f0a853ebc416a2b3e88f2071fd9a2bb26cda2ea856aa0fb3a80a87d2fb6136fab85e36c5b38d8242c373882c408cd5fd482a0c9816fc32203a10fbfce0e200ccfd9ee307c5e1224",
+        ),
+    ];
+
+    /// UFVK of account 0 of the all-zero seed under coin type 0, from the
+    /// librustzcash test suite
+    const UFVK: &str = "uview12z384wdq76ceewlsu0esk7d97qnd23v2qnvhujxtcf2lsq8g4hwzpx44fwxssnm5tg8skyh4tnc8gydwxefnnm0hd0a6c6etmj0pp9jqkdsllkr70u8gpf7ndsfqcjlqn6dec3faumzqlqcmtjf8vp92h7kj38ph2786zx30hq2wru8ae3excdwc8w0z3t9fuw7mt7xy5sn6s4e45kwm0cjp70wytnensgdnev286t3vew3yuwt2hcz865y037k30e428dvgne37xvyeal2vu8yjnznphf9t2rw3gdp0hk5zwq00ws8f3l3j5n3qkqgsyzrwx4qzmgq0xwwk4vz2r6vtsykgz089jncvycmem3535zjwvvtvjw8v98y0d5ydwte575gjm7a7k";
+
+    #[test]
+    fn full_viewing_key_matches_vectors() {
+        for (sk, fvk) in KEY_VECTORS {
+            let sk = hex::decode(sk).unwrap();
+            assert_eq!(hex::encode(orchard_full_viewing_key(&sk).unwrap()), fvk);
+        }
+        assert!(matches!(orchard_full_viewing_key(&[1u8; 31]), Err(OmniError::InvalidLength(_))));
+    }
+
+    #[test]
+    fn zip32_derivation_matches_reference_ufvk() {
+        // m/32'/0'/0' of the all-zero seed
+        let mut key = master_key(&[0u8; 32]).unwrap();
+        for index in [ZIP32_PURPOSE, 0, 0] {
+            key = derive_child(&key, index).unwrap();
+        }
+
+        let items = unified::decode_fvk_items(UFVK, Network::Mainnet).unwrap();
+        let (_, orchard_fvk) = items.iter().find(|(typecode, _)| *typecode == 3).unwrap();
+        assert_eq!(&orchard_full_viewing_key(&key[..32]).unwrap(), orchard_fvk);
+
+        // The exported path is the same derivation under coin type 133
+        let mut key = master_key(&[0u8; 32]).unwrap();
+        for index in [ZIP32_PURPOSE, 133, 5] {
+            key = derive_child(&key, index).unwrap();
+        }
+        assert_eq!(orchard_spending_key(&[0u8; 32], 5).unwrap(), key[..32]);
+        assert!(matches!(orchard_spending_key(&[0u8; 32], HARDENED), Err(OmniError::InvalidIndex(_))));
+    }
+}
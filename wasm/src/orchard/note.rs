@@ -0,0 +1,293 @@
+//! Orchard note commitments and nullifiers (Zcash protocol spec §4.16, §5.4.8.4)
+
+use ff::{FromUniformBytes, PrimeField};
+use group::{Curve, Group, GroupEncoding};
+use pasta_curves::{arithmetic::CurveExt, pallas};
+use wasm_bindgen::prelude::*;
+
+use super::{poseidon, sinsemilla, ORCHARD_PERSONALIZATION};
+use crate::{prf_expand_wide, OmniError};
+
+/// Sinsemilla domain of NoteCommit^Orchard
+const NOTE_COMMITMENT_PERSONALIZATION: &str = "z.cash:Orchard-NoteCommit";
+
+/// Hash-to-curve domain of DiversifyHash^Orchard
+const KEY_DIVERSIFICATION_PERSONALIZATION: &str = "z.cash:Orchard-gd";
+
+/// Bits of rho and psi committed to (ℓ^Orchard_base)
+const BASE_BITS: usize = 255;
+
+/// Compute the Orchard note commitment NoteCommit^Orchard_rcm(g_d, pk_d, v, rho, psi)
+/// as a 32-byte compressed Pallas point. Its x-coordinate cmx, the value
+/// stored in the note commitment tree, is these bytes with the top bit cleared.
+#[wasm_bindgen]
+pub fn orchard_note_commitment(
+    diversifier: &[u8],
+    pk_d: &[u8],
+    value: u64,
+    rho: &[u8],
+    psi: &[u8],
+    rcm: &[u8],
+) -> Result<Vec<u8>, OmniError> {
+    let diversifier: [u8; 11] = diversifier
+        .try_into()
+        .map_err(|_| OmniError::InvalidLength("Diversifier must be 11 bytes".into()))?;
+    let pk_d = parse_point(pk_d, "Invalid Orchard transmission key")?;
+    let rho = parse_base(rho, "rho")?;
+    let psi = parse_base(psi, "psi")?;
+    let rcm: [u8; 32] = rcm
+        .try_into()
+        .map_err(|_| OmniError::InvalidLength("rcm must be 32 bytes".into()))?;
+    let rcm = Option::from(pallas::Scalar::from_repr(rcm))
+        .ok_or_else(|| OmniError::InvalidScalar("Invalid rcm".into()))?;
+
+    // g★_d || pk★_d || I2LEBSP_64(v) || I2LEBSP_255(rho) || I2LEBSP_255(psi)
+    let mut message = Vec::with_capacity(256 + 256 + 64 + 2 * BASE_BITS);
+    message.extend(bits_le(&diversify_hash(&diversifier).to_bytes(), 256));
+    message.extend(bits_le(&pk_d.to_bytes(), 256));
+    message.extend(bits_le(&value.to_le_bytes(), 64));
+    message.extend(bits_le(&rho.to_repr(), BASE_BITS));
+    message.extend(bits_le(&psi.to_repr(), BASE_BITS));
+
+    let cm = sinsemilla::commit(NOTE_COMMITMENT_PERSONALIZATION, &message, &rcm);
+    Ok(cm.to_affine().to_bytes().to_vec())
+}
+
+/// Derive an Orchard nullifier
+/// nf = Extract_P([(PRF^nfOrchard_nk(rho) + psi) mod r_P] K^Orchard + cm), with
+/// PRF^nfOrchard the Poseidon hash and cm the compressed commitment point
+/// returned by `orchard_note_commitment`
+#[wasm_bindgen]
+pub fn orchard_nullifier(nk: &[u8], rho: &[u8], psi: &[u8], cm: &[u8]) -> Result<Vec<u8>, OmniError> {
+    let nk = parse_base(nk, "nk")?;
+    let rho = parse_base(rho, "rho")?;
+    let psi = parse_base(psi, "psi")?;
+    let cm = parse_point(cm, "Invalid note commitment")?;
+
+    // The base field is smaller than the scalar field, so this never reduces
+    let scalar = pallas::Scalar::from_repr((poseidon::hash(nk, rho) + psi).to_repr())
+        .expect("Pallas base field elements are below r_P");
+    let k = pallas::Point::hash_to_curve(ORCHARD_PERSONALIZATION)(b"K");
+
+    Ok(sinsemilla::extract_p(&(k * scalar + cm)).to_repr().to_vec())
+}
+
+/// Derive an Orchard note's commitment trapdoor
+/// rcm = ToScalar^Orchard(PRF^expand(rseed, [0x05] || rho))
+#[wasm_bindgen]
+pub fn orchard_rseed_to_rcm(rseed: &[u8], rho: &[u8]) -> Result<Vec<u8>, OmniError> {
+    let expanded = expand_rseed(rseed, 0x05, rho)?;
+    Ok(pallas::Scalar::from_uniform_bytes(&expanded).to_repr().to_vec())
+}
+
+/// Derive an Orchard note's psi = ToBase^Orchard(PRF^expand(rseed, [0x09] || rho))
+#[wasm_bindgen]
+pub fn orchard_rseed_to_psi(rseed: &[u8], rho: &[u8]) -> Result<Vec<u8>, OmniError> {
+    let expanded = expand_rseed(rseed, 0x09, rho)?;
+    Ok(pallas::Base::from_uniform_bytes(&expanded).to_repr().to_vec())
+}
+
+fn expand_rseed(rseed: &[u8], tag: u8, rho: &[u8]) -> Result<[u8; 64], OmniError> {
+    if rseed.len() != 32 {
+        return Err(OmniError::InvalidLength("rseed must be 32 bytes".into()));
+    }
+    parse_base(rho, "rho")?;
+
+    Ok(prf_expand_wide(rseed, &[&[tag], rho].concat()))
+}
+
+/// DiversifyHash^Orchard(d), replacing an identity result with the hash of
+/// the empty string
+fn diversify_hash(d: &[u8; 11]) -> pallas::Point {
+    let hasher = pallas::Point::hash_to_curve(KEY_DIVERSIFICATION_PERSONALIZATION);
+    let g_d = hasher(d);
+    if bool::from(g_d.is_identity()) {
+        hasher(&[])
+    } else {
+        g_d
+    }
+}
+
+fn parse_base(bytes: &[u8], name: &str) -> Result<pallas::Base, OmniError> {
+    let repr: [u8; 32] = bytes
+        .try_into()
+        .map_err(|_| OmniError::InvalidLength(format!("{} must be 32 bytes", name)))?;
+    Option::from(pallas::Base::from_repr(repr))
+        .ok_or_else(|| OmniError::InvalidEncoding(format!("{} is not a canonical Pallas base field element", name)))
+}
+
+fn parse_point(bytes: &[u8], error: &str) -> Result<pallas::Point, OmniError> {
+    let repr: [u8; 32] = bytes
+        .try_into()
+        .map_err(|_| OmniError::InvalidPoint(error.into()))?;
+    Option::from(pallas::Point::from_bytes(&repr)).ok_or_else(|| OmniError::InvalidPoint(error.into()))
+}
+
+fn bits_le(bytes: &[u8], count: usize) -> impl Iterator<Item = bool> + '_ {
+    bytes
+        .iter()
+        .flat_map(|byte| (0..8).map(move |i| (byte >> i) & 1 == 1))
+        .take(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// d, pk_d, nk, v, rho, rseed, cmx and nf
+    type NoteVector = (
+        &'static str,
+        &'static str,
+        &'static str,
+        u64,
+        &'static str,
+        &'static str,
+        &'static str,
+        &'static str,
+    );
+
+    /// The default-address notes of the Orchard key component test vectors
+    const NOTE_VECTORS: [NoteVector; 10] = [
+        (
+            "8ff3386971cb64b8e77899",
+            "08dd8ebd7de92a68e586a34db8fea999efd2016fae76750afae7ee941646bcb9",
+            "9f2f826738945ad01f47f70db0c367c246c20c61ff5583948c39dea968fefd1b",
+            15643327852135767324,
+            "2cb5b406ed8985e18130ab33362697b0e4e4c763ccb8f676495c222f7fba1e31",
+            "defa3d5a57efc2e1e9b01a035587d5fb1a38e01d94903d3c3e0ad3360c1d3710",
+            "4502e339901e397717839167cbb4037e0ecf6813b51c81fe085a7b782f124228",
+            "1b32edbbe4d18f28876de262518ad31122701f8c0a52e98047a337876e7eea19",
+        ),
+        (
+            "7807ca650858814d5022a8",
+            "3d3de4d52c77fd0b630a40dc38212487b2ff6eeef56d8c6a6163e854aff04189",
+            "a8b73d979b6eaada8924bcbdc63a9ef4e87346f230aba6bbe1e2b43c5bea6b22",
+            4481649511318637270,
+            "a51b0052ad8084a8b9da948d320dadd64f5431e61ddf658d24ae67c22c8d1309",
+            "131fc00fe7f235734276d38d47f1e191e00c7a1d48af046827591e9733a97fa6",
+            "c7ad794c563e32cad47d47dcda7884692848dce29ba4febd93202b7305f90300",
+            "2cf067bc21d66320e51b9fbdc8ae031c2c96373db43b7b1a45056c00c65d4320",
+        ),
+        (
+            "6424f71a3ad197426498f4",
+            "eccb6a5780204237987232bc098f89acc475c3f74bd69e2f35d44736f48f3c14",
+            "04514ea048b94363dea7cb3be8d62582ac52922e0865f662743b05eae8715f17",
+            14496603531126387959,
+            "32b4f473f468a008e72389fc03880d780cb07fcfaabe3f1a84b27db59a4a153d",
+            "882d2b2103596555ed9494c6ac893c49723833ec8926c1039586a7afcf4a0d9c",
+            "03ce20cea194b7559a8a90471d28a3c053c3720ad49f40d27c2dcce335005616",
+            "16fa2c3497fc09ad90dd349202a24b69892dc80629b2d1bfebaf41708f0fb10c",
+        ),
+        (
+            "db8c305524bc0deaa85d97",
+            "04ea8c1320ffbbadfe96f0c6ff16b607111b5583bfb6f1ea45275ef2aa2d879b",
+            "cf36ad6a066cd213e1d767ab071dc1167885c4168bc2e2175448563ad13f333d",
+            6792346249443327211,
+            "4b192232ecb9f0c02411e52596bc5e90457e745939ffedbd12863ce71a02af11",
+            "7d417adb3d15cc54dcb1fce467500c6b8fb86b12b56da9c382857deecc40a98d",
+            "a9b11baf3034b65c6424841bfe023f8eda1313c30aa27de92e21a108316e8219",
+            "72d6308960351f7b26fa64603fe4dfd867bd5eb367ba2b7ca491c923c0ead222",
+        ),
+        (
+            "aae36e094de07bc16f898e",
+            "b6533dcbfff0f6c1ceefa84799bda3de7334326ccd65f7ce92ff3d9e6e1f140b",
+            "51baf333cff1f2d0c7e3cff4d301299dc1efe98300314a541938029b45cc1521",
+            4079549063511228677,
+            "2670dc82d39026c6cb4cd4b0f7f5aa2a4f5a5341ec5dd715406f2fdd2afa733f",
+            "5f641c8c21862a1bafce2609d9eecfa158cfb5cd79f88008e315dc7d8388e76c",
+            "0ffbca1d5921fa0a8c5116ae137e37f2c118d52125628d8a3f412ce0e6530e04",
+            "e62b8ed83540146cd23cac74eed7d773d80224a5aa30d68e35572ee883d1b704",
+        ),
+        (
+            "cc7ce734b075a01b92aaca",
+            "3da5273a5667c766b8231206180f158ac02af3f06ecca6ec7c38c75d33600320",
+            "9e997d9d269787268e092a7c85417da530ea42fac668a749af55dfb71cdbbe09",
+            5706402952489856202,
+            "a1df0e5b87b5bece477a709649e950060591394812951e1fe3895b8cc3d14d2c",
+            "f6556df6ed4b4ddd3d9a69f53357d7767f4f5ccbdbc596631277f8fecd08cb05",
+            "63cee37e3c7b4e6cc939a2e63ada74f85ea48ba07a4f92ccbd34faa42dfd4916",
+            "4c99bfa8c20dba59bb7347da16c43b73c88794c9ebcd0dd2b25ee7bb836f9520",
+        ),
+        (
+            "99af6bf3f475bde889aaca",
+            "acdcd348ca45ee583278303846ca078459d5be5c5dcf347e3b9a34cba124b4a3",
+            "fd3164c632bec94ce9fb2f302263b884abb9c10e55e448647f6798495c9d083f",
+            2558469029534639129,
+            "722db041a3ef66fa483afd3c2e19e59444a64add6df1d963f5dd5b5010d3d025",
+            "f0287c4cf19c75f33d51ddddba5d657b43ee8da645443814cc7329f3e9b4e54c",
+            "1e619e46bb62b61d4e1cf3622ea70a908de7f076ecf87f541e0b7b48ad4a2601",
+            "3b948db21608e9acb22a5417b98c0dedd527a96487814e6420cbff6e4eee4e31",
+        ),
+        (
+            "2fbe4b4b1edff33123ce65",
+            "eb2c6fee341eade07d7487997aa723697d05e62960df379c9e4a8d476dfac5bf",
+            "02ab995ce98f63025fb62428a0fbf52f2522e6a27261078a9f4d6a36a1c05d39",
+            15425828902564319772,
+            "736c23357c85f45791e1708029d9824d90704607f387a03e49bf983657443134",
+            "5a7877efaa8a08e73081ef8d62cb780ab6883a50a0d470190dfba10a857f8284",
+            "c8528f722cd3e47dc99e1e388056370815a9d037973d85cac7ea38b5a716fa3b",
+            "acc2ed2c7e3b197e5cdb4a576357d5f135391626c7a825d10aa260ae0b958128",
+        ),
+        (
+            "08df1d4b45c673a459ff58",
+            "268cc24b38a62880b6ee3cbcb85a712fa686cffca6db2feec5f3c3566f84218f",
+            "2591edf7ef4cf2184c34be93fcf612915042f15ab5084b14e166795b09cea133",
+            12606128263924155660,
+            "12f6b02fe806b94569cd4059f396bf29b99d0a40e5e1711ca944f72d436a102f",
+            "ca4b97693da0b086fe9d2e7162470d02e0f05d4bec9512bfb3f38327296efaa7",
+            "6a1195aa0536f60ecfaecbdf5374e494ea072a2b867b5f694340c96fc370a910",
+            "b0f1602a2b1af2fc55f15950a6838385e5e39fecfd05ccec799b75c65c8da235",
+        ),
+        (
+            "4c4064c47a5ca6e75d4644",
+            "f517174be258923278cf458908c0735649f1899db99c3ba9003f4ba30ab0d210",
+            "3e88f2071fd9a2bb26cda2ea856aa0fb3a80a87d2fb6136fab85e36c5b38d824",
+            625536973899669523,
+            "03fd69442eb7681ec2a05600054e92eed555028f21b6a155268a2dd6640a6930",
+            "1a52a38d4d9f9f957ae35af7167118141ce4c9be0a6a492fe79f1581a155fa3a",
+            "f70ebf0f5ee5da6c6cdeff8fec2f8eed65c88e6755daf114d554af1967a7f40a",
+            "95649728465e682ac057ad876294d700c27feba2f750922f955185706261c30c",
+        ),
+    ];
+
+    #[test]
+    fn note_commitment_and_nullifier_match_vectors() {
+        for (d, pk_d, nk, v, rho, rseed, cmx, nf) in NOTE_VECTORS {
+            let [d, pk_d, nk, rho, rseed] = [d, pk_d, nk, rho, rseed].map(|h| hex::decode(h).unwrap());
+            let rcm = orchard_rseed_to_rcm(&rseed, &rho).unwrap();
+            let psi = orchard_rseed_to_psi(&rseed, &rho).unwrap();
+
+            let cm = orchard_note_commitment(&d, &pk_d, v, &rho, &psi, &rcm).unwrap();
+            let mut x = cm.clone();
+            x[31] &= 0x7f;
+            assert_eq!(hex::encode(x), cmx);
+
+            // PRF^nfOrchard is Poseidon(nk, rho), so these also pin down Poseidon
+            assert_eq!(hex::encode(orchard_nullifier(&nk, &rho, &psi, &cm).unwrap()), nf);
+        }
+    }
+
+    #[test]
+    fn malformed_note_fields_are_rejected() {
+        let (d, pk_d, nk, v, rho, rseed, ..) = NOTE_VECTORS[0];
+        let [d, pk_d, nk, rho, rseed] = [d, pk_d, nk, rho, rseed].map(|h| hex::decode(h).unwrap());
+        let rcm = orchard_rseed_to_rcm(&rseed, &rho).unwrap();
+        let psi = orchard_rseed_to_psi(&rseed, &rho).unwrap();
+        let cm = orchard_note_commitment(&d, &pk_d, v, &rho, &psi, &rcm).unwrap();
+
+        // p, the Pallas base field modulus, is not a canonical base field element
+        let p = hex::decode("01000000ed302d991bf94c09fc98462200000000000000000000000000000040").unwrap();
+        assert!(matches!(
+            orchard_note_commitment(&d, &pk_d, v, &p, &psi, &rcm),
+            Err(OmniError::InvalidEncoding(_))
+        ));
+        assert!(matches!(orchard_nullifier(&nk, &rho, &p, &cm), Err(OmniError::InvalidEncoding(_))));
+        assert!(matches!(orchard_rseed_to_psi(&rseed, &p), Err(OmniError::InvalidEncoding(_))));
+        assert!(matches!(
+            orchard_note_commitment(&d[..10], &pk_d, v, &rho, &psi, &rcm),
+            Err(OmniError::InvalidLength(_))
+        ));
+        assert!(matches!(orchard_nullifier(&nk, &rho, &psi, &[0xff; 32]), Err(OmniError::InvalidPoint(_))));
+        assert!(matches!(orchard_rseed_to_rcm(&rseed[..31], &rho), Err(OmniError::InvalidLength(_))));
+    }
+}
@@ -0,0 +1,160 @@
+//! Poseidon P128Pow5T3 over the Pallas base field, as used by PRF^nfOrchard
+//! (Zcash protocol spec §5.4.1.10)
+//!
+//! The round constants and MDS matrix are generated with the Grain LFSR of
+//! the Poseidon reference implementation rather than embedded as tables.
+
+use std::sync::OnceLock;
+
+use ff::{Field, FromUniformBytes, PrimeField};
+use pasta_curves::pallas;
+
+/// State width; the rate is 2 with one capacity element
+const WIDTH: usize = 3;
+
+const FULL_ROUNDS: usize = 8;
+
+const PARTIAL_ROUNDS: usize = 56;
+
+/// Bits in a Pallas base field element
+const FIELD_BITS: u16 = 255;
+
+struct Constants {
+    round_constants: Vec<[pallas::Base; WIDTH]>,
+    mds: [[pallas::Base; WIDTH]; WIDTH],
+}
+
+/// Poseidon(a, b) with ConstantLength<2> domain separation
+pub(super) fn hash(a: pallas::Base, b: pallas::Base) -> pallas::Base {
+    // The capacity element encodes the message length: 2 * 2^64
+    let mut state = [a, b, pallas::Base::from_u128(2 << 64)];
+    permute(&mut state);
+    state[0]
+}
+
+fn permute(state: &mut [pallas::Base; WIDTH]) {
+    let constants = constants();
+    let half_full = FULL_ROUNDS / 2;
+
+    for (round, rcs) in constants.round_constants.iter().enumerate() {
+        let full = round < half_full || round >= half_full + PARTIAL_ROUNDS;
+        for (word, rc) in state.iter_mut().zip(rcs) {
+            *word += rc;
+        }
+        if full {
+            state.iter_mut().for_each(|word| *word = sbox(*word));
+        } else {
+            state[0] = sbox(state[0]);
+        }
+
+        let mut mixed = [pallas::Base::ZERO; WIDTH];
+        for (out, row) in mixed.iter_mut().zip(&constants.mds) {
+            *out = row.iter().zip(state.iter()).map(|(m, word)| *m * word).sum();
+        }
+        *state = mixed;
+    }
+}
+
+fn sbox(x: pallas::Base) -> pallas::Base {
+    x.pow_vartime([5])
+}
+
+fn constants() -> &'static Constants {
+    static CONSTANTS: OnceLock<Constants> = OnceLock::new();
+    CONSTANTS.get_or_init(|| {
+        let mut grain = Grain::new();
+
+        let round_constants = (0..FULL_ROUNDS + PARTIAL_ROUNDS)
+            .map(|_| [(); WIDTH].map(|_| grain.next_field_element()))
+            .collect();
+
+        // Cauchy matrix 1 / (x_i + y_j) from the first 2 * WIDTH distinct draws
+        let (xs, ys) = loop {
+            let values: Vec<pallas::Base> = (0..2 * WIDTH).map(|_| grain.next_field_element_without_rejection()).collect();
+            let distinct = values.iter().enumerate().all(|(i, a)| values[..i].iter().all(|b| a != b));
+            if distinct {
+                break (values[..WIDTH].to_vec(), values[WIDTH..].to_vec());
+            }
+        };
+        let mut mds = [[pallas::Base::ZERO; WIDTH]; WIDTH];
+        for (row, x) in mds.iter_mut().zip(&xs) {
+            for (entry, y) in row.iter_mut().zip(&ys) {
+                *entry = (*x + y).invert().expect("x_i + y_j is nonzero for distinct draws");
+            }
+        }
+
+        Constants { round_constants, mds }
+    })
+}
+
+/// The 80-bit Grain LFSR of the Poseidon paper, in self-shrinking mode
+struct Grain {
+    state: [bool; 80],
+}
+
+impl Grain {
+    fn new() -> Grain {
+        let mut state = [true; 80];
+        let mut set_bits = |offset: usize, len: usize, value: u16| {
+            for i in 0..len {
+                state[offset + len - 1 - i] = (value >> i) & 1 == 1;
+            }
+        };
+        // Prime field, x^alpha S-box, field size, width, R_F, R_P
+        set_bits(0, 2, 1);
+        set_bits(2, 4, 0);
+        set_bits(6, 12, FIELD_BITS);
+        set_bits(18, 12, WIDTH as u16);
+        set_bits(30, 10, FULL_ROUNDS as u16);
+        set_bits(40, 10, PARTIAL_ROUNDS as u16);
+
+        let mut grain = Grain { state };
+        for _ in 0..160 {
+            grain.clock();
+        }
+        grain
+    }
+
+    /// b_{i+80} = b_{i+62} + b_{i+51} + b_{i+38} + b_{i+23} + b_{i+13} + b_i
+    fn clock(&mut self) -> bool {
+        let s = &self.state;
+        let bit = s[62] ^ s[51] ^ s[38] ^ s[23] ^ s[13] ^ s[0];
+        self.state.rotate_left(1);
+        self.state[79] = bit;
+        bit
+    }
+
+    /// Output the second bit of each pair whose first bit is 1
+    fn next_bit(&mut self) -> bool {
+        while !self.clock() {
+            self.clock();
+        }
+        self.clock()
+    }
+
+    /// The next FIELD_BITS output bits, most significant first, as a
+    /// little-endian integer
+    fn next_repr(&mut self) -> [u8; 32] {
+        let mut repr = [0u8; 32];
+        for i in (0..FIELD_BITS as usize).rev() {
+            if self.next_bit() {
+                repr[i / 8] |= 1 << (i % 8);
+            }
+        }
+        repr
+    }
+
+    fn next_field_element(&mut self) -> pallas::Base {
+        loop {
+            if let Some(element) = Option::from(pallas::Base::from_repr(self.next_repr())) {
+                return element;
+            }
+        }
+    }
+
+    fn next_field_element_without_rejection(&mut self) -> pallas::Base {
+        let mut wide = [0u8; 64];
+        wide[..32].copy_from_slice(&self.next_repr());
+        pallas::Base::from_uniform_bytes(&wide)
+    }
+}
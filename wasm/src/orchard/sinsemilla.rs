@@ -0,0 +1,53 @@
+//! Sinsemilla hash and commitments over Pallas (Zcash protocol spec §5.4.1.9)
+
+use std::sync::OnceLock;
+
+use group::Curve;
+use pasta_curves::arithmetic::{CurveAffine, CurveExt};
+use pasta_curves::pallas;
+
+/// Bits per message chunk
+const K: usize = 10;
+
+const Q_PERSONALIZATION: &str = "z.cash:SinsemillaQ";
+
+const S_PERSONALIZATION: &str = "z.cash:SinsemillaS";
+
+/// SinsemillaCommit_r(D, M) = SinsemillaHashToPoint(D || "-M", M) + [r] GroupHash^P(D || "-r", "")
+pub(super) fn commit(domain: &str, message: &[bool], r: &pallas::Scalar) -> pallas::Point {
+    let blinding_base = pallas::Point::hash_to_curve(&format!("{}-r", domain))(&[]);
+    hash_to_point(&format!("{}-M", domain), message) + blinding_base * r
+}
+
+/// SinsemillaHashToPoint(D, M): starting from Q(D), fold in each 10-bit chunk
+/// as Acc = (Acc + S(m_i)) + Acc, the message zero-padded to whole chunks
+fn hash_to_point(domain: &str, message: &[bool]) -> pallas::Point {
+    let q = pallas::Point::hash_to_curve(Q_PERSONALIZATION)(domain.as_bytes());
+    let table = s_table();
+
+    message.chunks(K).fold(q, |acc, chunk| {
+        let index = chunk
+            .iter()
+            .enumerate()
+            .fold(0usize, |index, (i, &bit)| index | (usize::from(bit) << i));
+        (acc + table[index]) + acc
+    })
+}
+
+/// S(j) = GroupHash^P("z.cash:SinsemillaS", I2LEOSP32(j)) for every chunk value
+fn s_table() -> &'static [pallas::Point] {
+    static TABLE: OnceLock<Vec<pallas::Point>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let hasher = pallas::Point::hash_to_curve(S_PERSONALIZATION);
+        (0..1u32 << K).map(|j| hasher(&j.to_le_bytes())).collect()
+    })
+}
+
+/// Extract_P: the x-coordinate, or 0 for the identity
+pub(super) fn extract_p(point: &pallas::Point) -> pallas::Base {
+    point
+        .to_affine()
+        .coordinates()
+        .map(|coordinates| *coordinates.x())
+        .unwrap_or(pallas::Base::zero())
+}
@@ -0,0 +1,403 @@
+//! Sapling output descriptions (Zcash protocol spec §4.7.2, ZIP-212)
+
+use chacha20poly1305::{aead::Aead, ChaCha20Poly1305, KeyInit, Nonce};
+use group::GroupEncoding;
+use js_sys::{Array, Object, Reflect, Uint8Array};
+use jubjub::{ExtendedPoint, Fr};
+use rand::{rngs::OsRng, RngCore};
+use wasm_bindgen::prelude::*;
+
+use crate::memo::MEMO_SIZE;
+use crate::note_encryption::{
+    aead_decrypt, bytes_field, ka_agree, kdf_sapling, valid_plaintext, ENC_CIPHERTEXT_SIZE, LEAD_BYTE_ZIP212,
+    NOTE_PLAINTEXT_SIZE,
+};
+use crate::{
+    bytes_to_scalar, ct_eq, decode_sapling_address, diversifier_to_point, note_commitment, parse_non_identity_point,
+    parse_subgroup_point, pedersen, prf_expand_to_scalar, try_random_scalar, value_commitment, OmniError,
+};
+
+/// BLAKE2b personalization for PRF^ock
+const PRF_OCK_PERSONALIZATION: &[u8; 16] = b"Zcash_Derive_ock";
+
+/// pk_d (32) || esk (32) followed by the 16-byte Poly1305 tag
+const OUT_CIPHERTEXT_SIZE: usize = 80;
+
+/// Build a Sapling output to `address`, returning
+/// `{ cv, cmu, epk, enc_ciphertext, out_ciphertext, rcv }`. The note's rcm and
+/// esk are derived from `rseed` per ZIP-212; rcv is sampled randomly and
+/// returned for the binding signature, throwing `RandomnessUnavailable` if
+/// the platform RNG fails.
+#[wasm_bindgen]
+pub fn build_sapling_output(
+    address: &str,
+    value: u64,
+    memo: &[u8],
+    ovk: &[u8],
+    rseed: &[u8],
+) -> Result<JsValue, OmniError> {
+    let output = sapling_output(address, value, memo, ovk, rseed, &mut OsRng)?;
+
+    let result = Object::new();
+    let fields: [(&str, &[u8]); 6] = [
+        ("cv", &output.cv),
+        ("cmu", &output.cmu),
+        ("epk", &output.epk),
+        ("enc_ciphertext", &output.enc_ciphertext),
+        ("out_ciphertext", &output.out_ciphertext),
+        ("rcv", &output.rcv.to_bytes()),
+    ];
+    for (name, bytes) in fields {
+        Reflect::set(&result, &name.into(), &Uint8Array::from(bytes))
+            .map_err(|_| OmniError::EncodingFailed("Failed to build result object".into()))?;
+    }
+    Ok(result.into())
+}
+
+/// The fields `build_sapling_output` returns
+struct SaplingOutput {
+    cv: [u8; 32],
+    cmu: [u8; 32],
+    epk: [u8; 32],
+    enc_ciphertext: Vec<u8>,
+    out_ciphertext: Vec<u8>,
+    rcv: Fr,
+}
+
+/// Build the output behind `build_sapling_output`, sampling rcv from `rng`
+fn sapling_output<R: RngCore>(
+    address: &str,
+    value: u64,
+    memo: &[u8],
+    ovk: &[u8],
+    rseed: &[u8],
+    rng: &mut R,
+) -> Result<SaplingOutput, OmniError> {
+    if memo.len() != MEMO_SIZE {
+        return Err(OmniError::InvalidLength("Memo must be 512 bytes".into()));
+    }
+    if ovk.len() != 32 {
+        return Err(OmniError::InvalidLength("Outgoing viewing key must be 32 bytes".into()));
+    }
+    if rseed.len() != 32 {
+        return Err(OmniError::InvalidLength("rseed must be 32 bytes".into()));
+    }
+
+    let address = decode_sapling_address(address)?;
+    let diversifier = address.diversifier();
+    let pk_d = address.pk_d();
+
+    let mut d = [0u8; 11];
+    d.copy_from_slice(&diversifier);
+    let g_d = diversifier_to_point(&d)?;
+    let pk_d_point = parse_subgroup_point(&pk_d, "Invalid transmission key")?;
+
+    let rcm = rseed_rcm(rseed);
+    let esk = rseed_esk(rseed);
+    let rcv = try_random_scalar(rng)?;
+
+    let cv = value_commitment(value, &rcv).to_bytes();
+    let cmu = pedersen::extract_u(&note_commitment(&g_d.to_bytes(), &pk_d, value, &rcm));
+    let epk = (g_d * esk).to_bytes();
+
+    // leadbyte || d || v || rseed || memo
+    let mut plaintext = Vec::with_capacity(NOTE_PLAINTEXT_SIZE);
+    plaintext.push(LEAD_BYTE_ZIP212);
+    plaintext.extend_from_slice(&diversifier);
+    plaintext.extend_from_slice(&value.to_le_bytes());
+    plaintext.extend_from_slice(rseed);
+    plaintext.extend_from_slice(memo);
+
+    let shared_secret = ka_agree(&esk, &ExtendedPoint::from(pk_d_point));
+    let enc_ciphertext = encrypt(&kdf_sapling(&shared_secret, &epk), &plaintext)?;
+
+    // The sender can recover pk_d || esk with the outgoing cipher key
+    let ock = prf_ock(ovk, &cv, &cmu, &epk);
+    let out_ciphertext = encrypt(&ock, &[&pk_d[..], &esk.to_bytes()[..]].concat())?;
+
+    Ok(SaplingOutput {
+        cv,
+        cmu,
+        epk,
+        enc_ciphertext,
+        out_ciphertext,
+        rcv,
+    })
+}
+
+/// Recover the 64-byte pk_d || esk from an output's out_ciphertext using the
+/// sender's outgoing viewing key
+#[wasm_bindgen]
+pub fn decrypt_out_ciphertext(
+    ovk: &[u8],
+    cv: &[u8],
+    cmu: &[u8],
+    epk: &[u8],
+    out_ciphertext: &[u8],
+) -> Result<Vec<u8>, OmniError> {
+    if ovk.len() != 32 || cv.len() != 32 || cmu.len() != 32 || epk.len() != 32 {
+        return Err(OmniError::InvalidLength("ovk, cv, cmu and epk must be 32 bytes".into()));
+    }
+    if out_ciphertext.len() != OUT_CIPHERTEXT_SIZE {
+        return Err(OmniError::InvalidLength("Out ciphertext must be 80 bytes".into()));
+    }
+
+    let ock = prf_ock(ovk, &to_array(cv), &to_array(cmu), &to_array(epk));
+    aead_decrypt(&ock, out_ciphertext)
+        .ok_or_else(|| OmniError::DecryptionFailed("Out ciphertext authentication failed".into()))
+}
+
+/// Recover the notes a wallet sent, for rebuilding its history from an
+/// outgoing viewing key. Each `{ cv, cmu, epk, enc_ciphertext, out_ciphertext }`
+/// output whose out_ciphertext opens under `ovk` yields pk_d and esk, which
+/// then decrypt enc_ciphertext; returns `{ index, pk_d, plaintext }` for each
+/// recovered note whose epk and cmu match its plaintext.
+#[wasm_bindgen]
+pub fn scan_sent_outputs(ovk: &[u8], outputs: JsValue) -> Result<JsValue, OmniError> {
+    if ovk.len() != 32 {
+        return Err(OmniError::InvalidLength("Outgoing viewing key must be 32 bytes".into()));
+    }
+    if !Array::is_array(&outputs) {
+        return Err(OmniError::InvalidEncoding("Outputs must be an array".into()));
+    }
+
+    let matches = Array::new();
+    for (index, output) in Array::from(&outputs).iter().enumerate() {
+        let cv = bytes_field(&output, "cv")?;
+        let cmu = bytes_field(&output, "cmu")?;
+        let epk = bytes_field(&output, "epk")?;
+        let enc_ciphertext = bytes_field(&output, "enc_ciphertext")?;
+        let out_ciphertext = bytes_field(&output, "out_ciphertext")?;
+        if cv.len() != 32 || cmu.len() != 32 || epk.len() != 32 {
+            return Err(OmniError::InvalidLength("cv, cmu and epk must be 32 bytes".into()));
+        }
+        if enc_ciphertext.len() != ENC_CIPHERTEXT_SIZE {
+            return Err(OmniError::InvalidLength("Encrypted ciphertext must be 580 bytes".into()));
+        }
+        if out_ciphertext.len() != OUT_CIPHERTEXT_SIZE {
+            return Err(OmniError::InvalidLength("Out ciphertext must be 80 bytes".into()));
+        }
+
+        if let Some((pk_d, plaintext)) = recover_sent_note(ovk, &cv, &cmu, &epk, &enc_ciphertext, &out_ciphertext) {
+            let found = Object::new();
+            Reflect::set(&found, &"index".into(), &(index as u32).into())
+                .and_then(|_| Reflect::set(&found, &"pk_d".into(), &Uint8Array::from(&pk_d[..])))
+                .and_then(|_| Reflect::set(&found, &"plaintext".into(), &Uint8Array::from(&plaintext[..])))
+                .map_err(|_| OmniError::EncodingFailed("Failed to build result object".into()))?;
+            matches.push(&found);
+        }
+    }
+    Ok(matches.into())
+}
+
+/// Derive a note's commitment trapdoor rcm from its 32-byte ZIP-212 rseed
+#[wasm_bindgen]
+pub fn rseed_to_rcm(rseed: &[u8]) -> Result<Vec<u8>, OmniError> {
+    if rseed.len() != 32 {
+        return Err(OmniError::InvalidLength("rseed must be 32 bytes".into()));
+    }
+    Ok(rseed_rcm(rseed).to_bytes().to_vec())
+}
+
+/// Derive a note's ephemeral secret key esk from its 32-byte ZIP-212 rseed
+#[wasm_bindgen]
+pub fn rseed_to_esk(rseed: &[u8]) -> Result<Vec<u8>, OmniError> {
+    if rseed.len() != 32 {
+        return Err(OmniError::InvalidLength("rseed must be 32 bytes".into()));
+    }
+    Ok(rseed_esk(rseed).to_bytes().to_vec())
+}
+
+/// Open out_ciphertext with ovk, then enc_ciphertext with the recovered
+/// pk_d and esk, returning `None` unless every check passes
+fn recover_sent_note(
+    ovk: &[u8],
+    cv: &[u8],
+    cmu: &[u8],
+    epk: &[u8],
+    enc_ciphertext: &[u8],
+    out_ciphertext: &[u8],
+) -> Option<([u8; 32], Vec<u8>)> {
+    let epk = to_array(epk);
+    let ock = prf_ock(ovk, &to_array(cv), &to_array(cmu), &epk);
+    let recovered = aead_decrypt(&ock, out_ciphertext)?;
+    let (pk_d, esk) = recovered.split_at(32);
+
+    let pk_d_point = parse_non_identity_point(pk_d, "Invalid transmission key").ok()?;
+    let esk = bytes_to_scalar(esk).ok()?;
+    let shared_secret = ka_agree(&esk, &ExtendedPoint::from(pk_d_point));
+    let plaintext = aead_decrypt(&kdf_sapling(&shared_secret, &epk), enc_ciphertext)?;
+
+    // leadbyte || d || v || rseed || memo; the note must be the one committed
+    // to, and epk must be [esk] g_d for the esk we were given
+    let mut d = [0u8; 11];
+    d.copy_from_slice(&plaintext[1..12]);
+    let g_d = diversifier_to_point(&d).ok()?;
+    if !valid_plaintext(&plaintext, &epk) || (g_d * esk).to_bytes() != epk {
+        return None;
+    }
+
+    let value = u64::from_le_bytes(plaintext[12..20].try_into().ok()?);
+    let rcm = match plaintext[0] {
+        LEAD_BYTE_ZIP212 => rseed_rcm(&plaintext[20..52]),
+        _ => bytes_to_scalar(&plaintext[20..52]).ok()?,
+    };
+    let expected_cmu = pedersen::extract_u(&note_commitment(&g_d.to_bytes(), pk_d, value, &rcm));
+    if !ct_eq(&expected_cmu, cmu) {
+        return None;
+    }
+
+    Some((to_array(pk_d), plaintext))
+}
+
+/// rcm = ToScalar(PRF^expand(rseed, [0x04]))
+fn rseed_rcm(rseed: &[u8]) -> Fr {
+    prf_expand_to_scalar(rseed, &[0x04])
+}
+
+/// esk = ToScalar(PRF^expand(rseed, [0x05]))
+pub(crate) fn rseed_esk(rseed: &[u8]) -> Fr {
+    prf_expand_to_scalar(rseed, &[0x05])
+}
+
+/// PRF^ock(ovk, cv, cmu, epk) = BLAKE2b-256("Zcash_Derive_ock", ovk || cv || cmu || epk)
+fn prf_ock(ovk: &[u8], cv: &[u8; 32], cmu: &[u8; 32], epk: &[u8; 32]) -> [u8; 32] {
+    let hash = blake2b_simd::Params::new()
+        .hash_length(32)
+        .personal(PRF_OCK_PERSONALIZATION)
+        .to_state()
+        .update(ovk)
+        .update(cv)
+        .update(cmu)
+        .update(epk)
+        .finalize();
+
+    let mut ock = [0u8; 32];
+    ock.copy_from_slice(hash.as_bytes());
+    ock
+}
+
+fn to_array(bytes: &[u8]) -> [u8; 32] {
+    let mut array = [0u8; 32];
+    array.copy_from_slice(bytes);
+    array
+}
+
+/// ChaCha20Poly1305 with the all-zero nonce used for Sapling note encryption
+fn encrypt(key: &[u8; 32], plaintext: &[u8]) -> Result<Vec<u8>, OmniError> {
+    ChaCha20Poly1305::new(key.into())
+        .encrypt(&Nonce::default(), plaintext)
+        .map_err(|_| OmniError::EncryptionFailed("Note encryption failed".into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note_encryption::try_decrypt_note;
+    use crate::{crh_ivk, derive_default_address, zip32, Network};
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    const OVK: [u8; 32] = [0x0f; 32];
+    const RSEED: [u8; 32] = [0x5e; 32];
+
+    /// The default address and ivk of the ZIP-32 master key for seed 0x00..0x1f
+    fn recipient() -> (String, Fr) {
+        let dfvk = zip32::derive_master(&(0u8..32).collect::<Vec<_>>())
+            .unwrap()
+            .to_diversifiable_fvk()
+            .unwrap();
+        let address = derive_default_address(&dfvk, Network::Mainnet).unwrap();
+        (address, crh_ivk(&dfvk[..32], &dfvk[32..64]))
+    }
+
+    #[test]
+    fn output_decrypts_to_its_value_and_memo() {
+        let (address, ivk) = recipient();
+        let mut memo = [0u8; MEMO_SIZE];
+        memo[..5].copy_from_slice(b"hello");
+
+        let output = sapling_output(&address, 123_456, &memo, &OVK, &RSEED, &mut ChaCha20Rng::seed_from_u64(1)).unwrap();
+        assert_eq!(output.enc_ciphertext.len(), ENC_CIPHERTEXT_SIZE);
+        assert_eq!(output.out_ciphertext.len(), OUT_CIPHERTEXT_SIZE);
+        assert_eq!(output.cv, value_commitment(123_456, &output.rcv).to_bytes());
+
+        let plaintext = try_decrypt_note(&ivk.to_bytes(), &output.epk, &output.enc_ciphertext)
+            .unwrap()
+            .expect("the recipient's ivk opens the note");
+        assert_eq!(plaintext[0], LEAD_BYTE_ZIP212);
+        assert_eq!(u64::from_le_bytes(plaintext[12..20].try_into().unwrap()), 123_456);
+        assert_eq!(plaintext[20..52], RSEED);
+        assert_eq!(plaintext[52..], memo);
+
+        // The sender recovers pk_d || esk with their ovk
+        let recovered =
+            decrypt_out_ciphertext(&OVK, &output.cv, &output.cmu, &output.epk, &output.out_ciphertext).unwrap();
+        assert_eq!(recovered[32..], rseed_to_esk(&RSEED).unwrap());
+    }
+
+    #[test]
+    fn rseed_derives_fixed_rcm_and_esk() {
+        // ToScalar(BLAKE2b-512("Zcash_ExpandSeed", rseed || [tag])) for
+        // rseed = 0x00..0x1f, computed independently with Python's hashlib
+        let rseed: Vec<u8> = (0u8..32).collect();
+        assert_eq!(
+            hex::encode(rseed_to_rcm(&rseed).unwrap()),
+            "c78b3a389f3d8137413b875cdab7a21428a3e758e78aff2068f107229e5d300d"
+        );
+        assert_eq!(
+            hex::encode(rseed_to_esk(&rseed).unwrap()),
+            "977bf7e05dce705d7b93abe2adc8e7f927ed86c65abcc39548427dab9cb9770e"
+        );
+
+        assert!(matches!(rseed_to_rcm(&rseed[..31]), Err(OmniError::InvalidLength(_))));
+        assert!(matches!(rseed_to_esk(&[0; 33]), Err(OmniError::InvalidLength(_))));
+    }
+
+    #[test]
+    fn out_ciphertext_round_trips_and_rejects_mac_mismatch() {
+        let (address, _) = recipient();
+        let output =
+            sapling_output(&address, 5, &[0; MEMO_SIZE], &OVK, &RSEED, &mut ChaCha20Rng::seed_from_u64(2)).unwrap();
+        let open = |ovk: &[u8], out_ciphertext: &[u8]| {
+            decrypt_out_ciphertext(ovk, &output.cv, &output.cmu, &output.epk, out_ciphertext)
+        };
+
+        let recovered = open(&OVK, &output.out_ciphertext).unwrap();
+        assert_eq!(recovered[..32], crate::sapling_receiver_bytes(&address).unwrap()[11..]);
+        assert_eq!(recovered[32..], rseed_esk(&RSEED).to_bytes());
+
+        let mut tampered = output.out_ciphertext.clone();
+        tampered[OUT_CIPHERTEXT_SIZE - 1] ^= 1;
+        assert!(matches!(open(&OVK, &tampered), Err(OmniError::DecryptionFailed(_))));
+        assert!(matches!(open(&[0x10; 32], &output.out_ciphertext), Err(OmniError::DecryptionFailed(_))));
+        assert!(matches!(open(&OVK, &output.out_ciphertext[1..]), Err(OmniError::InvalidLength(_))));
+    }
+
+    #[test]
+    fn sent_note_is_recovered_from_a_built_output() {
+        let (address, _) = recipient();
+        let mut memo = [0u8; MEMO_SIZE];
+        memo[..4].copy_from_slice(b"sent");
+        let output = sapling_output(&address, 42_000, &memo, &OVK, &RSEED, &mut ChaCha20Rng::seed_from_u64(3)).unwrap();
+        let recover = |ovk: &[u8], cmu: &[u8], enc_ciphertext: &[u8]| {
+            recover_sent_note(ovk, &output.cv, cmu, &output.epk, enc_ciphertext, &output.out_ciphertext)
+        };
+
+        let (pk_d, plaintext) = recover(&OVK, &output.cmu, &output.enc_ciphertext).expect("the sender's ovk opens it");
+        let receiver = crate::sapling_receiver_bytes(&address).unwrap();
+        assert_eq!(pk_d[..], receiver[11..]);
+        assert_eq!(plaintext[0], LEAD_BYTE_ZIP212);
+        assert_eq!(plaintext[1..12], receiver[..11]);
+        assert_eq!(u64::from_le_bytes(plaintext[12..20].try_into().unwrap()), 42_000);
+        assert_eq!(plaintext[20..52], RSEED);
+        assert_eq!(plaintext[52..], memo);
+
+        // Another ovk, a commitment to a different note, or another output's
+        // ciphertext all fail
+        assert!(recover(&[0x10; 32], &output.cmu, &output.enc_ciphertext).is_none());
+        let other = sapling_output(&address, 42_001, &memo, &OVK, &RSEED, &mut ChaCha20Rng::seed_from_u64(3)).unwrap();
+        assert!(recover(&OVK, &other.cmu, &output.enc_ciphertext).is_none());
+        assert!(recover(&OVK, &output.cmu, &other.enc_ciphertext).is_none());
+    }
+}
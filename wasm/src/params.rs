@@ -0,0 +1,191 @@
+//! Per-network encoding parameters: address prefixes, version bytes and coin types
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::OmniError;
+
+/// Zcash network whose address encodings should be used
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Network {
+    Mainnet,
+    Testnet,
+    Regtest,
+}
+
+impl Network {
+    /// Bech32 human-readable part for Sapling payment addresses
+    pub(crate) fn sapling_hrp(self) -> &'static str {
+        match self {
+            Network::Mainnet => "zs",
+            Network::Testnet => "ztestsapling",
+            Network::Regtest => "zregtestsapling",
+        }
+    }
+
+    /// Bech32 human-readable part for Sapling extended full viewing keys
+    pub(crate) fn extended_fvk_hrp(self) -> &'static str {
+        match self {
+            Network::Mainnet => "zxviews",
+            Network::Testnet => "zxviewtestsapling",
+            Network::Regtest => "zxviewregtestsapling",
+        }
+    }
+
+    /// Bech32m human-readable part for Unified Addresses
+    pub(crate) fn unified_hrp(self) -> &'static str {
+        match self {
+            Network::Mainnet => "u",
+            Network::Testnet => "utest",
+            Network::Regtest => "uregtest",
+        }
+    }
+
+    /// Bech32m human-readable part for Unified Full Viewing Keys
+    pub(crate) fn unified_fvk_hrp(self) -> &'static str {
+        match self {
+            Network::Mainnet => "uview",
+            Network::Testnet => "uviewtest",
+            Network::Regtest => "uviewregtest",
+        }
+    }
+
+    /// Base58Check version prefix for P2PKH transparent addresses
+    pub(crate) fn p2pkh_version(self) -> [u8; 2] {
+        match self {
+            Network::Mainnet => [0x1C, 0xB8],
+            Network::Testnet | Network::Regtest => [0x1D, 0x25],
+        }
+    }
+
+    /// Base58Check version prefix for P2SH transparent addresses
+    pub(crate) fn p2sh_version(self) -> [u8; 2] {
+        match self {
+            Network::Mainnet => [0x1C, 0xBD],
+            Network::Testnet | Network::Regtest => [0x1C, 0xBA],
+        }
+    }
+
+    /// SLIP-44 coin type used in ZIP-32 and BIP-44 paths
+    pub(crate) fn coin_type(self) -> u32 {
+        match self {
+            Network::Mainnet => 133,
+            Network::Testnet | Network::Regtest => 1,
+        }
+    }
+
+    /// Wallet Import Format version byte for transparent private keys
+    pub(crate) fn wif_version(self) -> u8 {
+        match self {
+            Network::Mainnet => 0x80,
+            Network::Testnet | Network::Regtest => 0xEF,
+        }
+    }
+
+    /// Base58Check version prefix for Sprout payment addresses
+    pub(crate) fn sprout_version(self) -> [u8; 2] {
+        match self {
+            Network::Mainnet => [0x16, 0x9A],
+            Network::Testnet | Network::Regtest => [0x16, 0xB6],
+        }
+    }
+
+    /// Network byte of `encode_key_with_checksum` key backups
+    pub(crate) fn key_format_byte(self) -> u8 {
+        match self {
+            Network::Mainnet => 0x00,
+            Network::Testnet => 0x01,
+            Network::Regtest => 0x02,
+        }
+    }
+}
+
+/// Every network, for detecting which one an encoding belongs to
+pub(crate) const NETWORKS: [Network; 3] = [Network::Mainnet, Network::Testnet, Network::Regtest];
+
+/// Every encoding parameter of one network, as returned by `network_params`
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NetworkParams {
+    sapling_hrp: &'static str,
+    extended_fvk_hrp: &'static str,
+    unified_hrp: &'static str,
+    unified_fvk_hrp: &'static str,
+    p2pkh_version: [u8; 2],
+    p2sh_version: [u8; 2],
+    sprout_version: [u8; 2],
+    wif_version: u8,
+    coin_type: u32,
+}
+
+/// The prefixes and version bytes every encoder in this crate uses for
+/// `network`, as `{ saplingHrp, extendedFvkHrp, unifiedHrp, unifiedFvkHrp,
+/// p2pkhVersion, p2shVersion, sproutVersion, wifVersion, coinType }`
+#[wasm_bindgen]
+pub fn network_params(network: Network) -> Result<JsValue, OmniError> {
+    serde_wasm_bindgen::to_value(&NetworkParams::of(network)).map_err(|e| OmniError::EncodingFailed(e.to_string()))
+}
+
+impl NetworkParams {
+    /// The parameters behind `network_params`
+    fn of(network: Network) -> NetworkParams {
+        NetworkParams {
+            sapling_hrp: network.sapling_hrp(),
+            extended_fvk_hrp: network.extended_fvk_hrp(),
+            unified_hrp: network.unified_hrp(),
+            unified_fvk_hrp: network.unified_fvk_hrp(),
+            p2pkh_version: network.p2pkh_version(),
+            p2sh_version: network.p2sh_version(),
+            sprout_version: network.sprout_version(),
+            wif_version: network.wif_version(),
+            coin_type: network.coin_type(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The human-readable parts of `params`
+    fn hrps(params: &NetworkParams) -> [&'static str; 4] {
+        [
+            params.sapling_hrp,
+            params.extended_fvk_hrp,
+            params.unified_hrp,
+            params.unified_fvk_hrp,
+        ]
+    }
+
+    #[test]
+    fn mainnet_and_testnet_params_are_distinct() {
+        let mainnet = NetworkParams::of(Network::Mainnet);
+        assert_eq!(hrps(&mainnet), ["zs", "zxviews", "u", "uview"]);
+        assert_eq!(
+            (mainnet.p2pkh_version, mainnet.p2sh_version, mainnet.sprout_version),
+            ([0x1C, 0xB8], [0x1C, 0xBD], [0x16, 0x9A])
+        );
+        assert_eq!((mainnet.wif_version, mainnet.coin_type), (0x80, 133));
+
+        let testnet = NetworkParams::of(Network::Testnet);
+        assert_eq!(
+            hrps(&testnet),
+            ["ztestsapling", "zxviewtestsapling", "utest", "uviewtest"]
+        );
+        assert_eq!(
+            (testnet.p2pkh_version, testnet.p2sh_version, testnet.sprout_version),
+            ([0x1D, 0x25], [0x1C, 0xBA], [0x16, 0xB6])
+        );
+        assert_eq!((testnet.wif_version, testnet.coin_type), (0xEF, 1));
+
+        // No HRP of one network is another's, so decoders can tell them apart
+        let mut all: Vec<&str> = NETWORKS
+            .iter()
+            .flat_map(|&network| hrps(&NetworkParams::of(network)))
+            .collect();
+        all.sort_unstable();
+        all.dedup();
+        assert_eq!(all.len(), 4 * NETWORKS.len());
+    }
+}
@@ -0,0 +1,346 @@
+//! RedJubjub signatures (Zcash protocol spec §5.4.7)
+
+use group::GroupEncoding;
+use js_sys::{Array, Uint8Array};
+use jubjub::{ExtendedPoint, Fr, SubgroupPoint};
+use rand::{rngs::OsRng, RngCore};
+use wasm_bindgen::prelude::*;
+
+use crate::scalar::mod_r;
+use crate::{bytes_to_scalar, parse_point, parse_subgroup_point, pedersen, try_fill_random, try_random_scalar, OmniError};
+
+/// BLAKE2b personalization for H^*, the RedJubjub hash-to-scalar
+const REDJUBJUB_H_PERSONALIZATION: &[u8; 16] = b"Zcash_RedJubjubH";
+
+/// Sign a 32-byte sighash with the randomized spend authorizing key
+/// rsk = ask + alpha, returning the 64-byte R || S signature
+#[wasm_bindgen]
+pub fn sign_spend_auth(ask: &[u8], alpha: &[u8], sighash: &[u8]) -> Result<Vec<u8>, OmniError> {
+    if ask.len() != 32 || alpha.len() != 32 {
+        return Err(OmniError::InvalidLength("ask and alpha must be 32 bytes".into()));
+    }
+    if sighash.len() != 32 {
+        return Err(OmniError::InvalidLength("Sighash must be 32 bytes".into()));
+    }
+
+    let rsk = bytes_to_scalar(ask)? + bytes_to_scalar(alpha)?;
+    Ok(sign(&mut OsRng, &rsk, pedersen::spending_key_generator(), sighash)?.to_vec())
+}
+
+/// Randomize a spend validating key: rk = ak + [alpha] G
+#[wasm_bindgen]
+pub fn randomize_ak(ak: &[u8], alpha: &[u8]) -> Result<Vec<u8>, OmniError> {
+    if ak.len() != 32 || alpha.len() != 32 {
+        return Err(OmniError::InvalidLength("ak and alpha must be 32 bytes".into()));
+    }
+
+    let ak = parse_subgroup_point(ak, "Invalid spend validating key")?;
+    let alpha = bytes_to_scalar(alpha)?;
+
+    Ok((ak + pedersen::spending_key_generator() * alpha).to_bytes().to_vec())
+}
+
+/// Randomize a spend authorizing key: rsk = ask + alpha
+#[wasm_bindgen]
+pub fn randomize_ask(ask: &[u8], alpha: &[u8]) -> Result<Vec<u8>, OmniError> {
+    if ask.len() != 32 || alpha.len() != 32 {
+        return Err(OmniError::InvalidLength("ask and alpha must be 32 bytes".into()));
+    }
+
+    let rsk = bytes_to_scalar(ask)? + bytes_to_scalar(alpha)?;
+    Ok(rsk.to_bytes().to_vec())
+}
+
+/// Generate a uniformly random spend authorization randomizer alpha,
+/// throwing `RandomnessUnavailable` if the platform RNG fails
+#[wasm_bindgen]
+pub fn random_alpha() -> Result<Vec<u8>, OmniError> {
+    Ok(try_random_scalar(&mut OsRng)?.to_bytes().to_vec())
+}
+
+/// Verify a spend authorization signature against a randomized key rk
+#[wasm_bindgen]
+pub fn verify_spend_auth(rk: &[u8], sighash: &[u8], sig: &[u8]) -> Result<bool, OmniError> {
+    if rk.len() != 32 || sighash.len() != 32 {
+        return Err(OmniError::InvalidLength("rk and sighash must be 32 bytes".into()));
+    }
+    if sig.len() != 64 {
+        return Err(OmniError::InvalidLength("Signature must be 64 bytes".into()));
+    }
+
+    verify(rk, pedersen::spending_key_generator(), sighash, sig)
+}
+
+/// Sum the rcv trapdoors of a bundle's value commitments into the binding
+/// signing key bsk = Σ rcv_spend - Σ rcv_output
+#[wasm_bindgen]
+pub fn sum_rcv(spend_rcvs: JsValue, output_rcvs: JsValue) -> Result<Vec<u8>, OmniError> {
+    let spends = byte_arrays(&spend_rcvs, "Spend rcvs")?;
+    let outputs = byte_arrays(&output_rcvs, "Output rcvs")?;
+
+    Ok(binding_signing_key(&spends, &outputs)?.to_bytes().to_vec())
+}
+
+/// Compute the binding validating key
+/// bvk = Σ cv_spend - Σ cv_output - [valueBalance] V
+#[wasm_bindgen]
+pub fn compute_bvk(spend_cvs: JsValue, output_cvs: JsValue, value_balance: i64) -> Result<Vec<u8>, OmniError> {
+    let spends = byte_arrays(&spend_cvs, "Spend cvs")?;
+    let outputs = byte_arrays(&output_cvs, "Output cvs")?;
+
+    Ok(binding_validating_key(&spends, &outputs, value_balance)?.to_bytes().to_vec())
+}
+
+/// The bsk behind `sum_rcv`
+fn binding_signing_key(spends: &[Vec<u8>], outputs: &[Vec<u8>]) -> Result<Fr, OmniError> {
+    let mut bsk = Fr::zero();
+    for rcv in spends {
+        bsk += bytes_to_scalar(rcv)?;
+    }
+    for rcv in outputs {
+        bsk -= bytes_to_scalar(rcv)?;
+    }
+    Ok(bsk)
+}
+
+/// The bvk behind `compute_bvk`
+fn binding_validating_key(
+    spends: &[Vec<u8>],
+    outputs: &[Vec<u8>],
+    value_balance: i64,
+) -> Result<ExtendedPoint, OmniError> {
+    let invalid = || OmniError::InvalidPoint("Invalid value commitment".into());
+
+    let mut bvk = ExtendedPoint::identity();
+    for cv in spends {
+        bvk += parse_point(cv).ok_or_else(invalid)?;
+    }
+    for cv in outputs {
+        bvk -= parse_point(cv).ok_or_else(invalid)?;
+    }
+
+    let balance = Fr::from(value_balance.unsigned_abs());
+    let balance = if value_balance < 0 { -balance } else { balance };
+    bvk -= pedersen::value_commitment_value_generator() * balance;
+    Ok(bvk)
+}
+
+/// Sign a 32-byte sighash with the binding signing key bsk (see `sum_rcv`),
+/// returning the 64-byte R || S binding signature
+#[wasm_bindgen]
+pub fn compute_binding_signature(rcv_sum: &[u8], sighash: &[u8]) -> Result<Vec<u8>, OmniError> {
+    if rcv_sum.len() != 32 || sighash.len() != 32 {
+        return Err(OmniError::InvalidLength("rcv_sum and sighash must be 32 bytes".into()));
+    }
+
+    let bsk = bytes_to_scalar(rcv_sum)?;
+    Ok(sign(&mut OsRng, &bsk, pedersen::value_commitment_randomness_generator(), sighash)?.to_vec())
+}
+
+/// Verify a binding signature against bvk (see `compute_bvk`)
+#[wasm_bindgen]
+pub fn verify_binding_signature(bvk: &[u8], sighash: &[u8], sig: &[u8]) -> Result<bool, OmniError> {
+    if bvk.len() != 32 || sighash.len() != 32 {
+        return Err(OmniError::InvalidLength("bvk and sighash must be 32 bytes".into()));
+    }
+    if sig.len() != 64 {
+        return Err(OmniError::InvalidLength("Signature must be 64 bytes".into()));
+    }
+
+    verify(bvk, pedersen::value_commitment_randomness_generator(), sighash, sig)
+}
+
+/// RedDSA.Sign: R = [r] P, S = r + c * sk, with T drawn from `rng`
+pub(crate) fn sign<R: RngCore>(
+    rng: &mut R,
+    sk: &Fr,
+    basepoint: &SubgroupPoint,
+    msg: &[u8],
+) -> Result<[u8; 64], OmniError> {
+    // T is (l_H + 128) / 8 = 80 random bytes
+    let mut t = [0u8; 80];
+    try_fill_random(rng, &mut t)?;
+
+    let vk = (basepoint * sk).to_bytes();
+    let r = h_star(&[&t, &vk, msg]);
+    let r_bytes = (basepoint * r).to_bytes();
+    let c = h_star(&[&r_bytes, &vk, msg]);
+    let s = r + c * sk;
+
+    let mut signature = [0u8; 64];
+    signature[..32].copy_from_slice(&r_bytes);
+    signature[32..].copy_from_slice(&s.to_bytes());
+    Ok(signature)
+}
+
+/// RedDSA.Validate: [h_J] ([S] P - [c] vk - R) = O
+pub(crate) fn verify(
+    vk: &[u8],
+    basepoint: &SubgroupPoint,
+    msg: &[u8],
+    sig: &[u8],
+) -> Result<bool, OmniError> {
+    let vk_point = parse_point(vk)
+        .ok_or_else(|| OmniError::InvalidPoint("Invalid verification key".into()))?;
+    let r = parse_point(&sig[..32])
+        .ok_or_else(|| OmniError::InvalidSignature("Invalid signature R".into()))?;
+    let s = bytes_to_scalar(&sig[32..])
+        .map_err(|_| OmniError::InvalidSignature("Invalid signature S".into()))?;
+
+    let c = h_star(&[&sig[..32], vk, msg]);
+    let check = ExtendedPoint::from(basepoint * s) - vk_point * c - r;
+
+    Ok(bool::from(check.is_small_order()))
+}
+
+/// H^*(M) = BLAKE2b-512("Zcash_RedJubjubH", M) reduced to a scalar
+fn h_star(parts: &[&[u8]]) -> Fr {
+    let mut state = blake2b_simd::Params::new()
+        .hash_length(64)
+        .personal(REDJUBJUB_H_PERSONALIZATION)
+        .to_state();
+    for part in parts {
+        state.update(part);
+    }
+
+    mod_r(state.finalize().as_bytes())
+}
+
+/// Read an array of 32-byte `Uint8Array`s
+fn byte_arrays(values: &JsValue, name: &str) -> Result<Vec<Vec<u8>>, OmniError> {
+    if !Array::is_array(values) {
+        return Err(OmniError::InvalidEncoding(format!("{} must be an array", name)));
+    }
+
+    Array::from(values)
+        .iter()
+        .map(|value| {
+            if !value.is_instance_of::<Uint8Array>() {
+                return Err(OmniError::InvalidEncoding(format!("{} must be Uint8Arrays", name)));
+            }
+            let bytes = Uint8Array::new(&value).to_vec();
+            if bytes.len() != 32 {
+                return Err(OmniError::InvalidLength(format!("{} must be 32 bytes each", name)));
+            }
+            Ok(bytes)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scalar::mod_r;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    const SIGHASH: [u8; 32] = [0x42; 32];
+
+    fn ask() -> Fr {
+        mod_r(&[0x11u8; 64])
+    }
+
+    #[test]
+    fn spend_auth_signature_round_trips() {
+        let ask = ask();
+        let alpha = mod_r(&[0x22u8; 64]).to_bytes();
+        let ak = (pedersen::spending_key_generator() * ask).to_bytes();
+        let rk = randomize_ak(&ak, &alpha).unwrap();
+
+        let sig = sign_spend_auth(&ask.to_bytes(), &alpha, &SIGHASH).unwrap();
+        assert!(verify_spend_auth(&rk, &SIGHASH, &sig).unwrap());
+
+        // Valid only under rk, the sighash and the signature as produced
+        assert!(!verify_spend_auth(&ak, &SIGHASH, &sig).unwrap());
+        assert!(!verify_spend_auth(&rk, &[0x43; 32], &sig).unwrap());
+        let mut tampered = sig.clone();
+        tampered[32] ^= 1;
+        assert!(!verify_spend_auth(&rk, &SIGHASH, &tampered).unwrap());
+
+        // A non-canonical S is malformed rather than merely invalid
+        let mut non_canonical = sig.clone();
+        non_canonical[32..].copy_from_slice(&[0xff; 32]);
+        assert!(matches!(
+            verify_spend_auth(&rk, &SIGHASH, &non_canonical),
+            Err(OmniError::InvalidSignature(_))
+        ));
+    }
+
+    #[test]
+    fn signature_satisfies_the_reddsa_equation() {
+        let sk = ask();
+        let basepoint = pedersen::spending_key_generator();
+        let vk = (basepoint * sk).to_bytes();
+
+        let sig = sign(&mut ChaCha20Rng::from_seed([7; 32]), &sk, basepoint, &SIGHASH).unwrap();
+        assert_eq!(sig, sign(&mut ChaCha20Rng::from_seed([7; 32]), &sk, basepoint, &SIGHASH).unwrap());
+        assert_ne!(sig, sign(&mut ChaCha20Rng::from_seed([8; 32]), &sk, basepoint, &SIGHASH).unwrap());
+
+        // [S] P = R + [c] vk with c = H*(R || vk || M)
+        let r = parse_subgroup_point(&sig[..32], "R").unwrap();
+        let s = bytes_to_scalar(&sig[32..]).unwrap();
+        let c = mod_r(
+            blake2b_simd::Params::new()
+                .hash_length(64)
+                .personal(b"Zcash_RedJubjubH")
+                .hash(&[&sig[..32], &vk[..], &SIGHASH[..]].concat())
+                .as_bytes(),
+        );
+        assert_eq!(basepoint * s, r + basepoint * (c * sk));
+    }
+
+    #[test]
+    fn binding_signature_round_trips() {
+        let bsk = mod_r(&[0x33u8; 64]);
+        let bvk = (pedersen::value_commitment_randomness_generator() * bsk).to_bytes();
+
+        let sig = compute_binding_signature(&bsk.to_bytes(), &SIGHASH).unwrap();
+        assert!(verify_binding_signature(&bvk, &SIGHASH, &sig).unwrap());
+        assert!(!verify_binding_signature(&bvk, &[0; 32], &sig).unwrap());
+        // Binding signatures use R, not G, as the basepoint
+        assert!(!verify_spend_auth(&bvk, &SIGHASH, &sig).unwrap());
+    }
+
+    #[test]
+    fn randomized_keys_stay_paired() {
+        let ask = ask();
+        let ak = (pedersen::spending_key_generator() * ask).to_bytes();
+
+        for alpha in [Fr::zero(), Fr::one(), mod_r(&[0x44u8; 64])] {
+            let rsk = bytes_to_scalar(&randomize_ask(&ask.to_bytes(), &alpha.to_bytes()).unwrap()).unwrap();
+            let rk = randomize_ak(&ak, &alpha.to_bytes()).unwrap();
+            assert_eq!(rk, (pedersen::spending_key_generator() * rsk).to_bytes());
+
+            let sig = sign(&mut OsRng, &rsk, pedersen::spending_key_generator(), &SIGHASH).unwrap();
+            assert!(verify_spend_auth(&rk, &SIGHASH, &sig).unwrap());
+        }
+        assert_eq!(randomize_ak(&ak, &[0u8; 32]).unwrap(), ak);
+    }
+
+    #[test]
+    fn balanced_bundle_binding_signature_verifies() {
+        let rcv = |byte: u8| mod_r(&[byte; 64]);
+        let cv = |value: u64, rcv: &Fr| crate::value_commitment(value, rcv).to_bytes().to_vec();
+
+        // 1000 in, 950 out, so valueBalance is 50
+        let spend_rcvs = [rcv(1), rcv(2)];
+        let output_rcvs = [rcv(3), rcv(4)];
+        let spend_cvs = [cv(700, &spend_rcvs[0]), cv(300, &spend_rcvs[1])];
+        let output_cvs = [cv(600, &output_rcvs[0]), cv(350, &output_rcvs[1])];
+
+        let to_bytes = |rcvs: &[Fr]| rcvs.iter().map(|r| r.to_bytes().to_vec()).collect::<Vec<_>>();
+        let bsk = binding_signing_key(&to_bytes(&spend_rcvs), &to_bytes(&output_rcvs)).unwrap();
+        let bvk = binding_validating_key(&spend_cvs, &output_cvs, 50).unwrap().to_bytes();
+        assert_eq!(bvk, (pedersen::value_commitment_randomness_generator() * bsk).to_bytes());
+
+        let sig = compute_binding_signature(&bsk.to_bytes(), &SIGHASH).unwrap();
+        assert!(verify_binding_signature(&bvk, &SIGHASH, &sig).unwrap());
+
+        // A stated balance that does not match the commitments leaves a bvk
+        // the signature cannot verify against
+        for value_balance in [49, 51, -50] {
+            let bvk = binding_validating_key(&spend_cvs, &output_cvs, value_balance).unwrap().to_bytes();
+            assert!(!verify_binding_signature(&bvk, &SIGHASH, &sig).unwrap());
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! Byte-order-tagged Jubjub scalar encodings
+//!
+//! Every scalar argument in this crate is the 32-byte little-endian encoding
+//! read by `Fr::from_bytes`. Hash outputs and other libraries often produce
+//! big-endian values instead; wrapping bytes in `BeScalarBytes` and converting
+//! with `to_le` makes the byte order an explicit decision.
+
+use jubjub::Fr;
+use subtle::{ConstantTimeEq, CtOption};
+use wasm_bindgen::prelude::*;
+
+use crate::OmniError;
+
+/// A 32-byte little-endian scalar encoding
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LeScalarBytes([u8; 32]);
+
+/// A 32-byte big-endian scalar encoding
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BeScalarBytes([u8; 32]);
+
+#[wasm_bindgen]
+impl LeScalarBytes {
+    #[wasm_bindgen(constructor)]
+    pub fn new(bytes: &[u8]) -> Result<LeScalarBytes, OmniError> {
+        Ok(LeScalarBytes(to_array(bytes)?))
+    }
+
+    /// Reverse a big-endian encoding
+    pub fn from_be(be: &BeScalarBytes) -> LeScalarBytes {
+        be.to_le()
+    }
+
+    pub fn to_be(&self) -> BeScalarBytes {
+        let mut bytes = self.0;
+        bytes.reverse();
+        BeScalarBytes(bytes)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_vec()
+    }
+
+    /// Whether the encoding is a canonical scalar below r
+    pub fn is_canonical(&self) -> bool {
+        self.to_scalar().is_ok()
+    }
+}
+
+impl LeScalarBytes {
+    /// Parse the scalar this encodes, rejecting any encoding other than its
+    /// unique canonical one. `Fr::from_bytes` already refuses values >= r
+    /// rather than reducing them; the round trip makes that explicit. Both
+    /// checks run in constant time, since the scalar may be a secret key.
+    pub(crate) fn to_scalar(self) -> Result<Fr, OmniError> {
+        let scalar = Fr::from_bytes(&self.0).and_then(|scalar| CtOption::new(scalar, scalar.to_bytes().ct_eq(&self.0)));
+
+        Option::from(scalar)
+            .ok_or_else(|| OmniError::InvalidScalar("Non-canonical scalar encoding (must be below r)".into()))
+    }
+}
+
+/// Reduce a little-endian integer of any length modulo r. Up to 64 bytes is
+/// a single `Fr::from_bytes_wide`; longer inputs are folded in 32-byte limbs
+/// from the most significant end, acc = acc * 2^256 + limb.
+pub(crate) fn mod_r(bytes_le: &[u8]) -> Fr {
+    if bytes_le.len() <= 64 {
+        let mut wide = [0u8; 64];
+        wide[..bytes_le.len()].copy_from_slice(bytes_le);
+        return Fr::from_bytes_wide(&wide);
+    }
+
+    let mut limb_shift = [0u8; 64];
+    limb_shift[32] = 1;
+    let limb_shift = Fr::from_bytes_wide(&limb_shift);
+
+    bytes_le
+        .chunks(32)
+        .rev()
+        .fold(Fr::zero(), |acc, limb| acc * limb_shift + mod_r(limb))
+}
+
+#[wasm_bindgen]
+impl BeScalarBytes {
+    #[wasm_bindgen(constructor)]
+    pub fn new(bytes: &[u8]) -> Result<BeScalarBytes, OmniError> {
+        Ok(BeScalarBytes(to_array(bytes)?))
+    }
+
+    /// Reverse into the little-endian encoding the rest of the crate expects
+    pub fn to_le(&self) -> LeScalarBytes {
+        let mut bytes = self.0;
+        bytes.reverse();
+        LeScalarBytes(bytes)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_vec()
+    }
+}
+
+fn to_array(bytes: &[u8]) -> Result<[u8; 32], OmniError> {
+    bytes
+        .try_into()
+        .map_err(|_| OmniError::InvalidLength("Scalar must be 32 bytes".into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn big_endian_reversed_matches_little_endian() {
+        let scalar = mod_r(&[0x5au8; 64]);
+        let le = scalar.to_bytes();
+        let mut be = le;
+        be.reverse();
+
+        let from_be = BeScalarBytes::new(&be).unwrap().to_le();
+        assert_eq!(from_be, LeScalarBytes::new(&le).unwrap());
+        assert_eq!(from_be.to_scalar().unwrap(), scalar);
+        assert_eq!(LeScalarBytes::from_be(&BeScalarBytes::new(&be).unwrap()).to_be().to_bytes(), be);
+
+        // Passing big-endian 32 as little-endian reads 32 * 2^248, which is above r
+        let mut small_be = [0u8; 32];
+        small_be[31] = 32;
+        assert!(!LeScalarBytes::new(&small_be).unwrap().is_canonical());
+        assert_eq!(BeScalarBytes::new(&small_be).unwrap().to_le().to_scalar().unwrap(), Fr::from(32u64));
+
+        assert!(matches!(BeScalarBytes::new(&be[1..]), Err(OmniError::InvalidLength(_))));
+    }
+
+    #[test]
+    fn mod_r_reduces_around_the_modulus() {
+        // r's low byte is 0xb7, so small offsets from it never carry
+        let below = (-Fr::one()).to_bytes();
+        let mut r = below;
+        r[0] += 1;
+        let plus = |k: u8| {
+            let mut bytes = r;
+            bytes[0] += k;
+            bytes
+        };
+
+        assert_eq!(mod_r(&below), -Fr::one());
+        assert_eq!(mod_r(&r), Fr::zero());
+        assert_eq!(mod_r(&plus(1)), Fr::one());
+        assert_eq!(mod_r(&plus(0x48)), Fr::from(0x48u64));
+
+        // The same values zero-extended, and shifted into the high limbs of the
+        // 64-byte and folded (> 64 bytes) paths
+        for len in [64, 65, 96, 100] {
+            let mut wide = vec![0u8; len];
+            wide[..32].copy_from_slice(&plus(3));
+            assert_eq!(mod_r(&wide), Fr::from(3u64));
+
+            // (r + 3) * 2^(8 * (len - 32)) + (r - 1) = -1 mod r
+            let mut shifted = vec![0u8; len];
+            shifted[len - 32..].copy_from_slice(&plus(3));
+            shifted[..32].copy_from_slice(&below);
+            let shift = (0..(len - 32) * 8).fold(Fr::one(), |acc, _| acc.double());
+            assert_eq!(mod_r(&shifted), Fr::from(3u64) * shift - Fr::one());
+        }
+    }
+}
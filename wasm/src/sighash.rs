@@ -0,0 +1,385 @@
+//! ZIP-244 v5 transaction signature digests for transparent-only transactions
+
+use js_sys::{Array, Reflect, Uint8Array};
+use wasm_bindgen::prelude::*;
+
+use crate::unified::write_compact_size;
+use crate::OmniError;
+
+/// v5 transaction version with the fOverwintered bit set
+const TX_VERSION: u32 = 5 | (1 << 31);
+
+/// nVersionGroupId of v5 transactions
+const TX_VERSION_GROUP_ID: u32 = 0x26A7_270A;
+
+const SIGHASH_ALL: u8 = 0x01;
+
+/// A transparent input together with the coin it spends
+struct TxIn {
+    prevout_hash: Vec<u8>,
+    prevout_index: u32,
+    value: u64,
+    script_pubkey: Vec<u8>,
+    sequence: u32,
+}
+
+struct TxOut {
+    value: u64,
+    script_pubkey: Vec<u8>,
+}
+
+/// The fields of a v5 transaction with no Sapling or Orchard bundles
+struct TransparentTx {
+    consensus_branch_id: u32,
+    lock_time: u32,
+    expiry_height: u32,
+    inputs: Vec<TxIn>,
+    outputs: Vec<TxOut>,
+}
+
+/// Compute the ZIP-244 `SIGHASH_ALL` signature digest of a transparent-only
+/// v5 transaction for the input at `input_index`, or for a shielded signature
+/// when `input_index` is negative
+///
+/// `tx_data` is `{ consensus_branch_id, lock_time, expiry_height, inputs, outputs }`
+/// where each input is `{ prevout_hash, prevout_index, value, script_pubkey, sequence }`
+/// (`value` and `script_pubkey` describe the coin being spent) and each output
+/// is `{ value, script_pubkey }`. Values are bigints.
+#[wasm_bindgen]
+pub fn compute_sighash_v5(tx_data: JsValue, input_index: i32) -> Result<Vec<u8>, OmniError> {
+    let tx = parse_tx(&tx_data)?;
+
+    let input = match usize::try_from(input_index) {
+        Ok(index) if index < tx.inputs.len() => Some(index),
+        Ok(_) => return Err(OmniError::InvalidIndex("Input index out of range".into())),
+        Err(_) => None,
+    };
+    Ok(signature_digest(&tx, input).to_vec())
+}
+
+/// sighash = BLAKE2b-256("ZcashTxHash_" || branch_id,
+///     header_digest || transparent_sig_digest || sapling_digest || orchard_digest)
+fn signature_digest(tx: &TransparentTx, input: Option<usize>) -> [u8; 32] {
+    let mut personal = *b"ZcashTxHash_\0\0\0\0";
+    personal[12..].copy_from_slice(&tx.consensus_branch_id.to_le_bytes());
+
+    let transparent = if tx.inputs.is_empty() || is_coinbase(tx) {
+        transparent_digest(tx)
+    } else {
+        transparent_sig_digest(tx, input)
+    };
+
+    hash(
+        &personal,
+        &[
+            &header_digest(tx),
+            &transparent,
+            &hash(b"ZTxIdSaplingHash", &[]),
+            &hash(b"ZTxIdOrchardHash", &[]),
+        ],
+    )
+}
+
+fn header_digest(tx: &TransparentTx) -> [u8; 32] {
+    hash(
+        b"ZTxIdHeadersHash",
+        &[
+            &TX_VERSION.to_le_bytes(),
+            &TX_VERSION_GROUP_ID.to_le_bytes(),
+            &tx.consensus_branch_id.to_le_bytes(),
+            &tx.lock_time.to_le_bytes(),
+            &tx.expiry_height.to_le_bytes(),
+        ],
+    )
+}
+
+/// The txid transparent digest (T.2), also signed when there are no
+/// transparent inputs to commit to
+fn transparent_digest(tx: &TransparentTx) -> [u8; 32] {
+    if tx.inputs.is_empty() && tx.outputs.is_empty() {
+        return hash(b"ZTxIdTranspaHash", &[]);
+    }
+
+    hash(
+        b"ZTxIdTranspaHash",
+        &[&prevouts_digest(tx), &sequence_digest(tx), &outputs_digest(tx)],
+    )
+}
+
+/// The transparent digest (S.2) for signing the input at `index`, or for a
+/// shielded signature which commits to no particular input
+fn transparent_sig_digest(tx: &TransparentTx, input: Option<usize>) -> [u8; 32] {
+    let amounts: Vec<u8> = tx.inputs.iter().flat_map(|input| input.value.to_le_bytes()).collect();
+    let mut scripts = Vec::new();
+    for input in &tx.inputs {
+        write_script(&mut scripts, &input.script_pubkey);
+    }
+
+    let mut txin = Vec::new();
+    if let Some(index) = input {
+        let input = &tx.inputs[index];
+        txin.extend_from_slice(&outpoint(input));
+        txin.extend_from_slice(&input.value.to_le_bytes());
+        write_script(&mut txin, &input.script_pubkey);
+        txin.extend_from_slice(&input.sequence.to_le_bytes());
+    }
+
+    hash(
+        b"ZTxIdTranspaHash",
+        &[
+            &[SIGHASH_ALL],
+            &prevouts_digest(tx),
+            &hash(b"ZTxTrAmountsHash", &[&amounts]),
+            &hash(b"ZTxTrScriptsHash", &[&scripts]),
+            &sequence_digest(tx),
+            &outputs_digest(tx),
+            &hash(b"Zcash___TxInHash", &[&txin]),
+        ],
+    )
+}
+
+fn prevouts_digest(tx: &TransparentTx) -> [u8; 32] {
+    let prevouts: Vec<u8> = tx.inputs.iter().flat_map(outpoint).collect();
+    hash(b"ZTxIdPrevoutHash", &[&prevouts])
+}
+
+fn sequence_digest(tx: &TransparentTx) -> [u8; 32] {
+    let sequences: Vec<u8> = tx.inputs.iter().flat_map(|input| input.sequence.to_le_bytes()).collect();
+    hash(b"ZTxIdSequencHash", &[&sequences])
+}
+
+fn outputs_digest(tx: &TransparentTx) -> [u8; 32] {
+    let mut outputs = Vec::new();
+    for output in &tx.outputs {
+        outputs.extend_from_slice(&output.value.to_le_bytes());
+        write_script(&mut outputs, &output.script_pubkey);
+    }
+    hash(b"ZTxIdOutputsHash", &[&outputs])
+}
+
+/// A coinbase's single input spends the null outpoint
+fn is_coinbase(tx: &TransparentTx) -> bool {
+    tx.inputs.len() == 1
+        && tx.inputs[0].prevout_hash.iter().all(|&b| b == 0)
+        && tx.inputs[0].prevout_index == u32::MAX
+}
+
+/// prevout_hash (32) || prevout_index (4)
+fn outpoint(input: &TxIn) -> Vec<u8> {
+    let mut bytes = input.prevout_hash.clone();
+    bytes.extend_from_slice(&input.prevout_index.to_le_bytes());
+    bytes
+}
+
+fn write_script(out: &mut Vec<u8>, script: &[u8]) {
+    write_compact_size(out, script.len() as u64);
+    out.extend_from_slice(script);
+}
+
+fn hash(personalization: &[u8; 16], parts: &[&[u8]]) -> [u8; 32] {
+    let mut state = blake2b_simd::Params::new()
+        .hash_length(32)
+        .personal(personalization)
+        .to_state();
+    for part in parts {
+        state.update(part);
+    }
+
+    let mut digest = [0u8; 32];
+    digest.copy_from_slice(state.finalize().as_bytes());
+    digest
+}
+
+fn parse_tx(tx_data: &JsValue) -> Result<TransparentTx, OmniError> {
+    let inputs = array_field(tx_data, "inputs")?
+        .iter()
+        .map(|input| {
+            let prevout_hash = bytes_field(&input, "prevout_hash")?;
+            if prevout_hash.len() != 32 {
+                return Err(OmniError::InvalidLength("prevout_hash must be 32 bytes".into()));
+            }
+            Ok(TxIn {
+                prevout_hash,
+                prevout_index: u32_field(&input, "prevout_index")?,
+                value: u64_field(&input, "value")?,
+                script_pubkey: bytes_field(&input, "script_pubkey")?,
+                sequence: u32_field(&input, "sequence")?,
+            })
+        })
+        .collect::<Result<_, _>>()?;
+
+    let outputs = array_field(tx_data, "outputs")?
+        .iter()
+        .map(|output| {
+            Ok(TxOut {
+                value: u64_field(&output, "value")?,
+                script_pubkey: bytes_field(&output, "script_pubkey")?,
+            })
+        })
+        .collect::<Result<_, OmniError>>()?;
+
+    Ok(TransparentTx {
+        consensus_branch_id: u32_field(tx_data, "consensus_branch_id")?,
+        lock_time: u32_field(tx_data, "lock_time")?,
+        expiry_height: u32_field(tx_data, "expiry_height")?,
+        inputs,
+        outputs,
+    })
+}
+
+fn field(object: &JsValue, name: &str) -> Result<JsValue, OmniError> {
+    Reflect::get(object, &name.into())
+        .map_err(|_| OmniError::InvalidEncoding("Transaction data must be an object".into()))
+}
+
+fn array_field(object: &JsValue, name: &str) -> Result<Array, OmniError> {
+    let value = field(object, name)?;
+    if !Array::is_array(&value) {
+        return Err(OmniError::InvalidEncoding(format!("{} must be an array", name)));
+    }
+    Ok(Array::from(&value))
+}
+
+fn bytes_field(object: &JsValue, name: &str) -> Result<Vec<u8>, OmniError> {
+    let value = field(object, name)?;
+    if !value.is_instance_of::<Uint8Array>() {
+        return Err(OmniError::InvalidEncoding(format!("{} must be a Uint8Array", name)));
+    }
+    Ok(Uint8Array::new(&value).to_vec())
+}
+
+fn u32_field(object: &JsValue, name: &str) -> Result<u32, OmniError> {
+    field(object, name)?
+        .as_f64()
+        .filter(|n| n.fract() == 0.0 && (0.0..=u32::MAX as f64).contains(n))
+        .map(|n| n as u32)
+        .ok_or_else(|| OmniError::InvalidEncoding(format!("{} must be a 32-bit unsigned integer", name)))
+}
+
+fn u64_field(object: &JsValue, name: &str) -> Result<u64, OmniError> {
+    u64::try_from(field(object, name)?)
+        .map_err(|_| OmniError::InvalidEncoding(format!("{} must be a bigint", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(prevout_hash: &str, prevout_index: u32, value: u64, script_pubkey: &str, sequence: u32) -> TxIn {
+        TxIn {
+            prevout_hash: hex::decode(prevout_hash).unwrap(),
+            prevout_index,
+            value,
+            script_pubkey: hex::decode(script_pubkey).unwrap(),
+            sequence,
+        }
+    }
+
+    fn output(value: u64, script_pubkey: &str) -> TxOut {
+        TxOut {
+            value,
+            script_pubkey: hex::decode(script_pubkey).unwrap(),
+        }
+    }
+
+    fn digest(tx: &TransparentTx, input: Option<usize>) -> String {
+        hex::encode(signature_digest(tx, input))
+    }
+
+    /// Transparent-only transactions from the ZIP-244 test vectors, with the
+    /// spent coins' amounts and scripts
+    #[test]
+    fn matches_zip244_vectors() {
+        let two_inputs = TransparentTx {
+            consensus_branch_id: 0xc2d6d0b4,
+            lock_time: 4128891171,
+            expiry_height: 85847861,
+            inputs: vec![
+                input(
+                    "4b216b7023fadc2d25949c90037e71e3e550726d210a2c688342e52440635e9c",
+                    285100737,
+                    1561051182746413,
+                    "656a516aac516a6552",
+                    1251880568,
+                ),
+                input(
+                    "5fa87f0a956f5b85509960285c22627c59483a5a4c28cce4b156e551406a7ee8",
+                    2723567157,
+                    1535468271734483,
+                    "52",
+                    3784106819,
+                ),
+            ],
+            outputs: vec![
+                output(718154202847933, ""),
+                output(1063580770500266, "516a636563"),
+                output(1215829031775032, "53516365acac"),
+            ],
+        };
+        assert_eq!(
+            digest(&two_inputs, Some(1)),
+            "2bea7c00cf77fa59a63ab0bd3eb7b10659f8fc9e4c4894bafc37640e8655f562"
+        );
+        assert_eq!(
+            digest(&two_inputs, None),
+            "24a91d017e691fe7d580e3fc16872d612c14bfcbe5e2725e16d09ec0c1c91305"
+        );
+
+        let no_outputs = TransparentTx {
+            consensus_branch_id: 0xc2d6d0b4,
+            lock_time: 996967297,
+            expiry_height: 386439913,
+            inputs: vec![
+                input(
+                    "a460e968aa7109870bbed17df5f888c8ca1467ae17dbbcde31c1105cb5bda88a",
+                    2606790,
+                    1076763594431866,
+                    "006551ac65630053",
+                    3967942159,
+                ),
+                input(
+                    "58bf1e6d1bb7aaada41fba0bb588778a7f65202ad811ea73d26c74550395aff7",
+                    2081432915,
+                    316847576141144,
+                    "63520053",
+                    1195567010,
+                ),
+                input(
+                    "19a3d185b7e0a43a472e298ac0afdc5287d7ad124cd9405a62cd1ca08b282efe",
+                    3744004599,
+                    1780844721475339,
+                    "acac00656a6351",
+                    318744042,
+                ),
+            ],
+            outputs: vec![],
+        };
+        assert_eq!(
+            digest(&no_outputs, Some(2)),
+            "9d802c8ace5bc5f5b03c7dac8a29ef33424def82cbc4fc8400c0bb9d5e084147"
+        );
+        assert_eq!(
+            digest(&no_outputs, None),
+            "b5a18ff657839c6979c2f2afaeeaf2f5c1cf43df92f4a65755fa42ae2b7706ab"
+        );
+
+        // A coinbase signs the txid digest, so its shielded sighash is its txid
+        let coinbase = TransparentTx {
+            consensus_branch_id: 0xc2d6d0b4,
+            lock_time: 4156177758,
+            expiry_height: 224858798,
+            inputs: vec![input(
+                "0000000000000000000000000000000000000000000000000000000000000000",
+                u32::MAX,
+                0,
+                "",
+                4294967295,
+            )],
+            outputs: vec![output(2099984228183121, "656500")],
+        };
+        assert_eq!(
+            digest(&coinbase, None),
+            "6bf4efe77af69b7219475f60a0f792db0263e4e12fa1d9ee1a1b9a68540590da"
+        );
+    }
+}
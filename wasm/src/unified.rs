@@ -0,0 +1,449 @@
+//! ZIP-316 Unified Address and Unified Full Viewing Key encoding
+
+use bech32::{FromBase32, ToBase32};
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+use js_sys::{Object, Reflect, Uint8Array};
+use wasm_bindgen::prelude::*;
+
+use crate::f4jumble::{f4jumble, f4jumble_inv};
+use crate::{bip32, crh_ivk, find_ff1_diversifier, hash160, keys, Network, OmniError};
+
+/// Receiver typecodes, in the ascending order they are encoded in
+const P2PKH: u64 = 0x00;
+const P2SH: u64 = 0x01;
+const SAPLING: u64 = 0x02;
+const ORCHARD: u64 = 0x03;
+
+/// Full viewing key items: the transparent BIP-44 account-level chain code ||
+/// compressed public key, Sapling ak || nk || ovk || dk and Orchard ak || nk || rivk
+const FVK_ITEMS: [(u64, &str, usize); 3] = [
+    (P2PKH, "transparent", 65),
+    (SAPLING, "sapling", 128),
+    (ORCHARD, "orchard", 96),
+];
+
+/// The raw encoding is padded with the HRP, zero-extended to 16 bytes
+const PADDING_LEN: usize = 16;
+
+/// A known receiver: typecode, the field name seen from JavaScript and its length
+const RECEIVERS: [(u64, &str, usize); 4] = [
+    (P2PKH, "transparent", 20),
+    (P2SH, "p2sh", 20),
+    (SAPLING, "sapling", 43),
+    (ORCHARD, "orchard", 43),
+];
+
+/// Encode a Unified Address from an object with optional `orchard` (43 bytes),
+/// `sapling` (43 bytes), `transparent` (P2PKH hash160) and `p2sh` (script
+/// hash) receivers. At least one shielded receiver is required.
+#[wasm_bindgen]
+pub fn encode_unified_address(receivers: JsValue, network: Network) -> Result<String, OmniError> {
+    let mut items = Vec::new();
+    for (typecode, name, length) in RECEIVERS {
+        if let Some(data) = receiver_field(&receivers, name)? {
+            if data.len() != length {
+                return Err(OmniError::InvalidLength(format!("{} receiver must be {} bytes", name, length)));
+            }
+            items.push((typecode, data));
+        }
+    }
+    validate_items(&items)?;
+    encode_items(&items, network.unified_hrp())
+}
+
+/// Decode a Unified Address into an object with the typed receivers it
+/// contains. Receivers with unknown typecodes are skipped.
+#[wasm_bindgen]
+pub fn decode_unified_address(address: &str, network: Network) -> Result<JsValue, OmniError> {
+    items_to_object(&decode_items(address, network)?, &RECEIVERS, "receiver")
+}
+
+/// Encode a Unified Full Viewing Key from an object with optional `orchard`
+/// (96 bytes), `sapling` (128-byte diversifiable FVK) and `transparent`
+/// (65-byte account-level chain code || public key) items. At least one
+/// shielded item is required.
+#[wasm_bindgen]
+pub fn encode_unified_fvk(fvks: JsValue, network: Network) -> Result<String, OmniError> {
+    let mut items = Vec::new();
+    for (typecode, name, length) in FVK_ITEMS {
+        if let Some(data) = receiver_field(&fvks, name)? {
+            if data.len() != length {
+                return Err(OmniError::InvalidLength(format!("{} viewing key must be {} bytes", name, length)));
+            }
+            items.push((typecode, data));
+        }
+    }
+    validate_fvk_items(&items)?;
+    encode_items(&items, network.unified_fvk_hrp())
+}
+
+/// Decode a Unified Full Viewing Key into an object with the typed viewing
+/// keys it contains. Items with unknown typecodes are skipped.
+#[wasm_bindgen]
+pub fn decode_unified_fvk(ufvk: &str, network: Network) -> Result<JsValue, OmniError> {
+    items_to_object(&decode_fvk_items(ufvk, network)?, &FVK_ITEMS, "viewing key")
+}
+
+/// Derive the Unified Address of a Unified Full Viewing Key at the first
+/// diversifier index >= `diversifier_index` that is valid for its Sapling key
+///
+/// The address has the Sapling receiver and, if the key has a transparent
+/// item, the P2PKH receiver of the external key at the same index. Orchard
+/// items are ignored since Orchard address derivation is not supported yet.
+#[wasm_bindgen]
+pub fn unified_addresses_from_ufvk(ufvk: &str, diversifier_index: u32, network: Network) -> Result<String, OmniError> {
+    let items = decode_fvk_items(ufvk, network)?;
+    let item = |typecode| items.iter().find(|(code, _)| *code == typecode).map(|(_, data)| data);
+
+    let sapling = item(SAPLING)
+        .ok_or_else(|| OmniError::InvalidEncoding("Unified full viewing key has no Sapling viewing key".into()))?;
+    if sapling.len() != 128 {
+        return Err(OmniError::InvalidLength("sapling viewing key must be 128 bytes".into()));
+    }
+    let fvk = keys::FullViewingKey::from_bytes(sapling)?;
+    let ivk = crh_ivk(&fvk.ak(), &fvk.nk());
+    let (diversifier, index) = find_ff1_diversifier(&fvk.dk(), diversifier_index.into())?;
+
+    let mut receivers = Vec::new();
+    if let Some(transparent) = item(P2PKH) {
+        let index = u32::try_from(index)
+            .map_err(|_| OmniError::InvalidIndex("Diversifier index is too large for a transparent key".into()))?;
+        let public_key = bip32::derive_external_pubkey(transparent, index)?;
+        receivers.push((P2PKH, hash160(public_key.to_encoded_point(true).as_bytes()).to_vec()));
+    }
+    receivers.push((SAPLING, keys::PaymentAddress::from_diversifier(&ivk, diversifier)?.to_bytes()));
+
+    encode_items(&receivers, network.unified_hrp())
+}
+
+/// Build a JavaScript object from the items with known typecodes
+fn items_to_object(items: &[(u64, Vec<u8>)], known: &[(u64, &str, usize)], kind: &str) -> Result<JsValue, OmniError> {
+    let result = Object::new();
+    for (typecode, data) in items {
+        let Some(&(_, name, length)) = known.iter().find(|(code, _, _)| code == typecode) else {
+            continue;
+        };
+        if data.len() != length {
+            return Err(OmniError::InvalidLength(format!("{} {} must be {} bytes", name, kind, length)));
+        }
+        Reflect::set(&result, &name.into(), &Uint8Array::from(&data[..]))
+            .map_err(|_| OmniError::EncodingFailed("Failed to build result object".into()))?;
+    }
+    Ok(result.into())
+}
+
+/// Jumble and bech32m-encode typecode-ordered items
+fn encode_items(items: &[(u64, Vec<u8>)], hrp: &str) -> Result<String, OmniError> {
+    let mut raw = Vec::new();
+    for (typecode, data) in items {
+        write_compact_size(&mut raw, *typecode);
+        write_compact_size(&mut raw, data.len() as u64);
+        raw.extend_from_slice(data);
+    }
+    raw.extend_from_slice(&padding(hrp));
+
+    bech32::encode(hrp, f4jumble(&raw)?.to_base32(), bech32::Variant::Bech32m)
+        .map_err(|e| OmniError::EncodingFailed(e.to_string()))
+}
+
+/// Decode, unjumble and split a Unified Address into its receivers
+pub(crate) fn decode_items(address: &str, network: Network) -> Result<Vec<(u64, Vec<u8>)>, OmniError> {
+    let items = decode_container(address, network.unified_hrp())?;
+    validate_items(&items)?;
+    Ok(items)
+}
+
+/// Decode, unjumble and split a Unified Full Viewing Key into its items
+pub(crate) fn decode_fvk_items(ufvk: &str, network: Network) -> Result<Vec<(u64, Vec<u8>)>, OmniError> {
+    let items = decode_container(ufvk, network.unified_fvk_hrp())?;
+    validate_fvk_items(&items)?;
+    Ok(items)
+}
+
+/// Decode the bech32m, F4Jumble and padding layers shared by unified encodings
+fn decode_container(encoded: &str, expected_hrp: &str) -> Result<Vec<(u64, Vec<u8>)>, OmniError> {
+    let (hrp, data, variant) = bech32::decode(encoded)
+        .map_err(|e| OmniError::InvalidEncoding(format!("Invalid bech32m encoding: {}", e)))?;
+
+    if hrp != expected_hrp {
+        return Err(OmniError::InvalidEncoding("Invalid unified encoding prefix for network".into()));
+    }
+    if variant != bech32::Variant::Bech32m {
+        return Err(OmniError::InvalidEncoding("Unified encodings must use Bech32m".into()));
+    }
+
+    let jumbled = Vec::<u8>::from_base32(&data)
+        .map_err(|e| OmniError::InvalidEncoding(e.to_string()))?;
+    let raw = f4jumble_inv(&jumbled)?;
+
+    let (raw, tail) = raw.split_at(raw.len() - PADDING_LEN);
+    if tail != padding(&hrp) {
+        return Err(OmniError::InvalidEncoding("Invalid unified encoding padding".into()));
+    }
+
+    parse_items(raw)
+}
+
+/// Read an optional byte-array field, treating `undefined` and `null` as absent
+fn receiver_field(receivers: &JsValue, name: &str) -> Result<Option<Vec<u8>>, OmniError> {
+    let value = Reflect::get(receivers, &name.into())
+        .map_err(|_| OmniError::InvalidEncoding("Receivers must be an object".into()))?;
+
+    if value.is_undefined() || value.is_null() {
+        return Ok(None);
+    }
+    if !value.is_instance_of::<Uint8Array>() {
+        return Err(OmniError::InvalidEncoding(format!("{} receiver must be a Uint8Array", name)));
+    }
+    Ok(Some(Uint8Array::new(&value).to_vec()))
+}
+
+/// Items must be in strictly ascending typecode order, must not combine
+/// P2PKH and P2SH, and must include a non-transparent receiver
+fn validate_items(items: &[(u64, Vec<u8>)]) -> Result<(), OmniError> {
+    if items.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+        return Err(OmniError::InvalidEncoding("Duplicate or out-of-order receiver typecode".into()));
+    }
+    if items.iter().any(|(code, _)| *code == P2PKH) && items.iter().any(|(code, _)| *code == P2SH) {
+        return Err(OmniError::InvalidEncoding("Unified address cannot contain both P2PKH and P2SH".into()));
+    }
+    if items.iter().all(|(code, _)| *code == P2PKH || *code == P2SH) {
+        return Err(OmniError::InvalidEncoding("Unified address needs a shielded receiver".into()));
+    }
+    Ok(())
+}
+
+/// Items must be in strictly ascending typecode order, must not include P2SH
+/// and must include a non-transparent item
+fn validate_fvk_items(items: &[(u64, Vec<u8>)]) -> Result<(), OmniError> {
+    if items.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+        return Err(OmniError::InvalidEncoding("Duplicate or out-of-order viewing key typecode".into()));
+    }
+    if items.iter().any(|(code, _)| *code == P2SH) {
+        return Err(OmniError::InvalidEncoding("Unified full viewing key cannot contain P2SH".into()));
+    }
+    if items.iter().all(|(code, _)| *code == P2PKH) {
+        return Err(OmniError::InvalidEncoding("Unified full viewing key needs a shielded viewing key".into()));
+    }
+    Ok(())
+}
+
+/// Split a raw encoding into typecode || length || data items
+fn parse_items(mut raw: &[u8]) -> Result<Vec<(u64, Vec<u8>)>, OmniError> {
+    let mut items = Vec::new();
+    while !raw.is_empty() {
+        let typecode = read_compact_size(&mut raw)?;
+        let length = read_compact_size(&mut raw)?;
+
+        if length > raw.len() as u64 {
+            return Err(OmniError::InvalidEncoding("Truncated unified encoding item".into()));
+        }
+        let (data, rest) = raw.split_at(length as usize);
+        items.push((typecode, data.to_vec()));
+        raw = rest;
+    }
+    Ok(items)
+}
+
+fn padding(hrp: &str) -> [u8; PADDING_LEN] {
+    let mut padding = [0u8; PADDING_LEN];
+    padding[..hrp.len()].copy_from_slice(hrp.as_bytes());
+    padding
+}
+
+pub(crate) fn write_compact_size(out: &mut Vec<u8>, n: u64) {
+    match n {
+        0..=0xFC => out.push(n as u8),
+        0xFD..=0xFFFF => {
+            out.push(0xFD);
+            out.extend_from_slice(&(n as u16).to_le_bytes());
+        }
+        0x10000..=0xFFFF_FFFF => {
+            out.push(0xFE);
+            out.extend_from_slice(&(n as u32).to_le_bytes());
+        }
+        _ => {
+            out.push(0xFF);
+            out.extend_from_slice(&n.to_le_bytes());
+        }
+    }
+}
+
+/// Read a canonically encoded CompactSize, advancing `input`
+fn read_compact_size(input: &mut &[u8]) -> Result<u64, OmniError> {
+    let truncated = || OmniError::InvalidEncoding("Truncated CompactSize".into());
+
+    let (&first, rest) = input.split_first().ok_or_else(truncated)?;
+    let (width, min) = match first {
+        0xFD => (2, 0xFD),
+        0xFE => (4, 0x10000),
+        0xFF => (8, 0x1_0000_0000),
+        _ => {
+            *input = rest;
+            return Ok(first as u64);
+        }
+    };
+    if rest.len() < width {
+        return Err(truncated());
+    }
+
+    let mut bytes = [0u8; 8];
+    bytes[..width].copy_from_slice(&rest[..width]);
+    let n = u64::from_le_bytes(bytes);
+    if n < min {
+        return Err(OmniError::InvalidEncoding("Non-canonical CompactSize".into()));
+    }
+
+    *input = &rest[width..];
+    Ok(n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Receivers (typecode, hex) and the encoded address, from the ZIP-316
+    /// test vectors; the fifth carries an unknown typecode
+    const VECTORS: [(&[(u64, &str)], &str); 6] = [
+        (
+            &[
+                (P2PKH, "7bb83570b8fae146e03c5331a020b1e0892f631d"),
+                (SAPLING, "d8ef8293d26de832e7193f296ba1922d90f122c6135bc231eebd91efdb03b1a8606771cd4fd6480574d43e"),
+            ],
+            "u1l8xunezsvhq8fgzfl7404m450nwnd76zshscn6nfys7vyz2ywyh4cc5daaq0c7q2su5lqfh23sp7fkf3kt27ve5948mzpfdvckzaect2jtte308mkwlycj2u0eac077wu70vqcetkxf",
+        ),
+        (
+            &[
+                (P2PKH, "cad268758c5e71493066446b98e71df9d1d6a5ca"),
+                (SAPLING, "9f6e0bf90a18fc0b9b83ae9f23ad4358648638482b5def8975635b66fd8a708335f9235a3186ec0f033f84"),
+                (ORCHARD, "cecbe5e689a453a3fe10ccf7617e6c1fb382819d7fc9200a1f42092ac84a30378f8c1fb90dff71a6d5042d"),
+            ],
+            "u1pg2aaph7jp8rpf6yhsza25722sg5fcn3vaca6ze27hqjw7jvvhhuxkpcg0ge9xh6drsgdkda8qjq5chpehkcpxf87rnjryjqwymdheptpvnljqqrjqzjwkc2ma6hcq666kgwfytxwac8eyex6ndgr6ezte66706e3vaqrd25dzvzkc69kw0jgywtd0cmq52q5lkw6uh7hyvzjse8ksx",
+        ),
+        (
+            &[
+                (SAPLING, "88533c398a49c2513dc85162bf220abaf47dc983f14e908ddaaa7322dba16531bc62efe750fe575c8d149b"),
+                (ORCHARD, "953f3c78d103c32b60559299462ebb27348964b892acad10482fe502c99f0d524959ba7be4f188e3a27138"),
+            ],
+            "u1ay3aawlldjrmxqnjf5medr5ma6p3acnet464ht8lmwplq5cd3ugytcmlf96rrmtgwldc75x94qn4n8pgen36y8tywlq6yjk7lkf3fa8wzjrav8z2xpxqnrnmjxh8tmz6jhfh425t7f3vy6p4pd3zmqayq49efl2c4xydc0gszg660q9p",
+        ),
+        (
+            &[
+                (P2PKH, "871a089d446268aa7ac03d2a6f60ae70808f3974"),
+                (ORCHARD, "31844683a07bf8e30057902b0d23e2b2ce9cad0b22190238ca4f329da92c7979052b00f735cb210671bdb0"),
+            ],
+            "u1snf9yr883aj2hm8pksp9aymnqdwzy42rpzuffevj35hhxeckays5pcpeq7vy2mtgzlcuc4mnh9443qnuyje0yx6h59angywka4v2ap6kchh2j96ezf9w0c0auyz3wwts2lx5gmk2sk9",
+        ),
+        (
+            &[
+                (SAPLING, "da2672c010f7364df6fad49dd39be0e4d4be73c45e239448fcc385cc68094bf36ddbc4ec0219b567955556"),
+                (0xfffd, "d17d19f3355bcf73cecb8cb8a5da01307152f13936a270572670dc82d39026c6cb4cd4b0f7f5aa2a4f5a5341ec5dd715406f2fdd2afa733f5f641c8c21862a1bafce2609d9eecfa158cfb5cd79f88008e315dc7d8388e76c1782fd2795d18a763624c25fa959cc97489ce75745824b77868c53239cfbdf73ca"),
+            ],
+            "u1sem2gcey0emntrvxyjv8hyhq0w5fr4sxaj3cppgrfqgg6laydh8m78gy2cw2p54zzak3alnnsx4xjuhazpkrfcd90wl0c7ldj6y095hh5j6j2evry9vg5jqp4dyqpwqeryu7pes4sxyyyqwn6egs5daxk4473v9xpgzrwv5n0tvs93nlj4xpphq4vs2w8um9ph7zkte08t7fa509mnrt9apuhr22xq34mp2svjnq6rvfn0hg6lkehxtlj39vgjxjlkjfhx8rw2f02ckq8k5szcxsnhkgr2cqlmf2udl2gqdqr5t6",
+        ),
+        (
+            &[
+                (ORCHARD, "e340636542ece1c81285ed4eab448adbb5a8c0f4d386eeff337e88e6915f6c3ec1b6ea835a88d56612d2bd"),
+            ],
+            "u1ddnjsdcpm36r6aq79n3s68shjweksnmwtdltrh046s8m6xcws9ygyawalxx8n6hg6vegk0wh8zjnafxgh6msppjsljvyt0ynece3lvm0",
+        ),
+    ];
+
+    #[test]
+    fn unified_addresses_match_zip316_vectors() {
+        for (receivers, address) in VECTORS {
+            let items: Vec<(u64, Vec<u8>)> = receivers
+                .iter()
+                .map(|(typecode, data)| (*typecode, hex::decode(data).unwrap()))
+                .collect();
+
+            assert_eq!(encode_items(&items, Network::Mainnet.unified_hrp()).unwrap(), address);
+            assert_eq!(decode_items(address, Network::Mainnet).unwrap(), items);
+            assert!(decode_items(address, Network::Testnet).is_err());
+        }
+    }
+
+    #[test]
+    fn decode_items_rejects_invalid_receiver_sets() {
+        let sapling = (SAPLING, vec![0u8; 43]);
+        let p2pkh = (P2PKH, vec![0u8; 20]);
+        let p2sh = (P2SH, vec![0u8; 20]);
+        let hrp = Network::Mainnet.unified_hrp();
+
+        for items in [
+            // Oversized so the encoding reaches F4Jumble's 48-byte minimum
+            vec![(P2PKH, vec![0u8; 32])],
+            vec![p2pkh.clone(), p2sh, sapling.clone()],
+            vec![sapling.clone(), p2pkh],
+            vec![sapling.clone(), sapling],
+        ] {
+            let address = encode_items(&items, hrp).unwrap();
+            assert!(matches!(decode_items(&address, Network::Mainnet), Err(OmniError::InvalidEncoding(_))));
+        }
+    }
+
+    /// UFVKs of account 0 of the all-zero seed, with and without the
+    /// transparent item, from the librustzcash test suite
+    const UFVK_WITH_TRANSPARENT: &str = "uview1tg6rpjgju2s2j37gkgjq79qrh5lvzr6e0ed3n4sf4hu5qd35vmsh7avl80xa6mx7ryqce9hztwaqwrdthetpy4pc0kce25x453hwcmax02p80pg5savlg865sft9reat07c5vlactr6l2pxtlqtqunt2j9gmvr8spcuzf07af80h5qmut38h0gvcfa9k4rwujacwwca9vu8jev7wq6c725huv8qjmhss3hdj2vh8cfxhpqcm2qzc34msyrfxk5u6dqttt4vv2mr0aajreww5yufpk0gn4xkfm888467k7v6fmw7syqq6cceu078yw8xja502jxr0jgum43lhvpzmf7eu5dmnn6cr6f7p43yw8znzgxg598mllewnx076hljlvynhzwn5es94yrv65tdg3utuz2u3sras0wfcq4adxwdvlk387d22g3q98t5z74quw2fa4wed32escx8dwh4mw35t4jwf35xyfxnu83mk5s4kw2glkgsshmxk";
+    const UFVK_SHIELDED: &str = "uview12z384wdq76ceewlsu0esk7d97qnd23v2qnvhujxtcf2lsq8g4hwzpx44fwxssnm5tg8skyh4tnc8gydwxefnnm0hd0a6c6etmj0pp9jqkdsllkr70u8gpf7ndsfqcjlqn6dec3faumzqlqcmtjf8vp92h7kj38ph2786zx30hq2wru8ae3excdwc8w0z3t9fuw7mt7xy5sn6s4e45kwm0cjp70wytnensgdnev286t3vew3yuwt2hcz865y037k30e428dvgne37xvyeal2vu8yjnznphf9t2rw3gdp0hk5zwq00ws8f3l3j5n3qkqgsyzrwx4qzmgq0xwwk4vz2r6vtsykgz089jncvycmem3535zjwvvtvjw8v98y0d5ydwte575gjm7a7k";
+
+    #[test]
+    fn unified_fvks_match_reference_encodings() {
+        let items = decode_fvk_items(UFVK_WITH_TRANSPARENT, Network::Mainnet).unwrap();
+        let layout: Vec<_> = items.iter().map(|(typecode, data)| (*typecode, data.len())).collect();
+        assert_eq!(layout, [(P2PKH, 65), (SAPLING, 128), (ORCHARD, 96)]);
+        assert_eq!(encode_items(&items, "uview").unwrap(), UFVK_WITH_TRANSPARENT);
+
+        let shielded = decode_fvk_items(UFVK_SHIELDED, Network::Mainnet).unwrap();
+        assert_eq!(shielded, items[1..]);
+        assert_eq!(encode_items(&shielded, "uview").unwrap(), UFVK_SHIELDED);
+
+        // Sapling m/32'/0'/0' and transparent m/44'/133'/0'
+        let dfvk = crate::zip32::derive_master(&[0u8; 32])
+            .and_then(|m| m.derive_child(32))
+            .and_then(|k| k.derive_child(0))
+            .and_then(|k| k.derive_child(0))
+            .and_then(|k| k.to_diversifiable_fvk())
+            .unwrap();
+        assert_eq!(items[1].1, dfvk);
+        let external = bip32::derive_bip44_key(&[0u8; 32], 133, 0, 0).unwrap().public_key();
+        assert_eq!(bip32::derive_external_pubkey(&items[0].1, 0).unwrap(), external);
+
+        assert!(decode_fvk_items(UFVK_SHIELDED, Network::Testnet).is_err());
+        assert!(matches!(
+            decode_fvk_items(&UFVK_SHIELDED.replace("uview1", "u1"), Network::Mainnet),
+            Err(OmniError::InvalidEncoding(_))
+        ));
+        let transparent_only = vec![(P2PKH, items[0].1.clone())];
+        assert!(validate_fvk_items(&transparent_only).is_err());
+    }
+
+    #[test]
+    fn ufvk_addresses_decode_to_its_receivers() {
+        let items = decode_fvk_items(UFVK_WITH_TRANSPARENT, Network::Mainnet).unwrap();
+        let (transparent, sapling) = (&items[0].1, &items[1].1);
+        let fvk = keys::FullViewingKey::from_bytes(sapling).unwrap();
+
+        for start in [0, 1, 2, 10] {
+            let ua = unified_addresses_from_ufvk(UFVK_WITH_TRANSPARENT, start, Network::Mainnet).unwrap();
+            let receivers = decode_items(&ua, Network::Mainnet).unwrap();
+
+            // Both receivers are at the first valid Sapling index from `start`
+            let (_, index) = find_ff1_diversifier(&sapling[96..], start.into()).unwrap();
+            let index = u32::try_from(index).unwrap();
+            let public_key = bip32::derive_external_pubkey(transparent, index).unwrap();
+            assert_eq!(
+                receivers,
+                [
+                    (P2PKH, hash160(public_key.to_encoded_point(true).as_bytes()).to_vec()),
+                    (SAPLING, fvk.payment_address(index).unwrap().0.to_bytes()),
+                ]
+            );
+        }
+
+        let ua = unified_addresses_from_ufvk(UFVK_SHIELDED, 0, Network::Mainnet).unwrap();
+        let receivers = decode_items(&ua, Network::Mainnet).unwrap();
+        assert_eq!(receivers.len(), 1);
+        assert_eq!(receivers[0].0, SAPLING);
+    }
+}
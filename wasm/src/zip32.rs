@@ -0,0 +1,449 @@
+//! ZIP-32 hierarchical deterministic derivation of Sapling extended spending keys
+
+use std::cell::{OnceCell, RefCell};
+use std::collections::BTreeMap;
+
+use group::ff::Field;
+use jubjub::Fr;
+use wasm_bindgen::prelude::*;
+use zeroize::{DefaultIsZeroes, Zeroize, ZeroizeOnDrop};
+
+use crate::keys::{FullViewingKey, PaymentAddress, SpendingKey};
+use crate::{
+    bytes_to_scalar, crh_ivk, diversifiable_fvk, expand_spending_key, find_ff1_diversifier, prf_expand,
+    prf_expand_to_scalar, prf_expand_wide, Network, OmniError,
+};
+
+/// BLAKE2b personalization for the Sapling master key
+const ZIP32_SAPLING_MASTER_PERSONALIZATION: &[u8; 16] = b"ZcashIP32Sapling";
+
+/// BLAKE2b personalization for Sapling full viewing key fingerprints
+const ZIP32_SAPLING_FVFP_PERSONALIZATION: &[u8; 16] = b"ZcashSaplingFVFP";
+
+/// depth (1) || parent_fvk_tag (4) || child_index (4) || chain_code (32) || expsk (96) || dk (32)
+const EXTENDED_SPENDING_KEY_LENGTH: usize = 169;
+
+/// Child indices with this bit set are hardened
+const HARDENED: u32 = 1 << 31;
+
+/// A Sapling extended spending key as defined in ZIP-32
+///
+/// The key material is wiped when the struct is dropped (or freed from
+/// JavaScript).
+#[wasm_bindgen]
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub struct ExtendedSpendingKey {
+    depth: u8,
+    parent_fvk_tag: [u8; 4],
+    child_index: u32,
+    chain_code: [u8; 32],
+    expsk: [u8; 96],
+    dk: [u8; 32],
+}
+
+#[wasm_bindgen]
+impl ExtendedSpendingKey {
+    /// Decode a 169-byte serialized extended spending key
+    pub fn from_bytes(bytes: &[u8]) -> Result<ExtendedSpendingKey, OmniError> {
+        if bytes.len() != EXTENDED_SPENDING_KEY_LENGTH {
+            return Err(OmniError::InvalidLength("Extended spending key must be 169 bytes".into()));
+        }
+
+        let mut key = ExtendedSpendingKey {
+            depth: bytes[0],
+            parent_fvk_tag: [0u8; 4],
+            child_index: u32::from_le_bytes([bytes[5], bytes[6], bytes[7], bytes[8]]),
+            chain_code: [0u8; 32],
+            expsk: [0u8; 96],
+            dk: [0u8; 32],
+        };
+        key.parent_fvk_tag.copy_from_slice(&bytes[1..5]);
+        key.chain_code.copy_from_slice(&bytes[9..41]);
+        key.expsk.copy_from_slice(&bytes[41..137]);
+        key.dk.copy_from_slice(&bytes[137..169]);
+
+        // Only the master key may have depth 0, and it has no parent
+        if key.depth == 0 && (key.parent_fvk_tag != [0u8; 4] || key.child_index != 0) {
+            return Err(OmniError::InvalidEncoding("Invalid master key encoding".into()));
+        }
+        if key.depth > 0 && key.child_index & HARDENED == 0 {
+            return Err(OmniError::InvalidEncoding("Only hardened child keys are supported".into()));
+        }
+
+        bytes_to_scalar(&key.expsk[0..32])?;
+        bytes_to_scalar(&key.expsk[32..64])?;
+
+        Ok(key)
+    }
+
+    /// Serialize to the 169-byte ZIP-32 encoding
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(EXTENDED_SPENDING_KEY_LENGTH);
+        bytes.push(self.depth);
+        bytes.extend_from_slice(&self.parent_fvk_tag);
+        bytes.extend_from_slice(&self.child_index.to_le_bytes());
+        bytes.extend_from_slice(&self.chain_code);
+        bytes.extend_from_slice(&self.expsk);
+        bytes.extend_from_slice(&self.dk);
+        bytes
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn parent_fvk_tag(&self) -> Vec<u8> {
+        self.parent_fvk_tag.to_vec()
+    }
+
+    /// Child index including the hardened bit (0 for the master key)
+    #[wasm_bindgen(getter)]
+    pub fn child_index(&self) -> u32 {
+        self.child_index
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn chain_code(&self) -> Vec<u8> {
+        self.chain_code.to_vec()
+    }
+
+    /// The 96-byte ask || nsk || ovk expanded spending key
+    #[wasm_bindgen(getter)]
+    pub fn expanded_spending_key(&self) -> Vec<u8> {
+        self.expsk.to_vec()
+    }
+
+    /// The diversifier key
+    #[wasm_bindgen(getter)]
+    pub fn dk(&self) -> Vec<u8> {
+        self.dk.to_vec()
+    }
+
+    /// The 128-byte ak || nk || ovk || dk diversifiable full viewing key
+    pub fn to_diversifiable_fvk(&self) -> Result<Vec<u8>, OmniError> {
+        diversifiable_fvk(&self.expsk, &self.dk)
+    }
+
+    /// The 169-byte extended full viewing key: the same header and chain code
+    /// followed by ak || nk || ovk || dk
+    pub fn to_extended_fvk(&self) -> Result<Vec<u8>, OmniError> {
+        let mut bytes = self.to_bytes();
+        bytes.truncate(41);
+        bytes.extend_from_slice(&self.to_diversifiable_fvk()?);
+        Ok(bytes)
+    }
+
+    /// Derive the hardened child at `index` (the hardened bit is added here)
+    pub fn derive_child(&self, index: u32) -> Result<ExtendedSpendingKey, OmniError> {
+        if index >= HARDENED {
+            return Err(OmniError::InvalidIndex("Child index must be below 2^31".into()));
+        }
+        let child_index = index | HARDENED;
+
+        let depth = self
+            .depth
+            .checked_add(1)
+            .ok_or_else(|| OmniError::DerivationFailed("Maximum derivation depth reached".into()))?;
+
+        let ask = bytes_to_scalar(&self.expsk[0..32])?;
+        let nsk = bytes_to_scalar(&self.expsk[32..64])?;
+
+        // I = PRF^expand(c_par, [0x11] || expsk_par || dk_par || I2LEOSP32(i))
+        let mut t = Vec::with_capacity(133);
+        t.push(0x11);
+        t.extend_from_slice(&self.expsk);
+        t.extend_from_slice(&self.dk);
+        t.extend_from_slice(&child_index.to_le_bytes());
+        let i = prf_expand_wide(&self.chain_code, &t);
+        let (i_l, i_r) = i.split_at(32);
+
+        let ask_child = prf_expand_to_scalar(i_l, &[0x13]) + ask;
+        let nsk_child = prf_expand_to_scalar(i_l, &[0x14]) + nsk;
+        if bool::from(ask_child.is_zero()) {
+            return Err(OmniError::DerivationFailed("Derived spend authorizing key is zero".into()));
+        }
+
+        let mut expsk = [0u8; 96];
+        expsk[0..32].copy_from_slice(&ask_child.to_bytes());
+        expsk[32..64].copy_from_slice(&nsk_child.to_bytes());
+        expsk[64..96].copy_from_slice(&prf_expand_tagged(i_l, 0x15, &self.expsk[64..96]));
+
+        let mut chain_code = [0u8; 32];
+        chain_code.copy_from_slice(i_r);
+
+        Ok(ExtendedSpendingKey {
+            depth,
+            parent_fvk_tag: self.fvk_tag()?,
+            child_index,
+            chain_code,
+            expsk,
+            dk: prf_expand_tagged(i_l, 0x16, &self.dk),
+        })
+    }
+}
+
+impl ExtendedSpendingKey {
+    /// First 4 bytes of BLAKE2b-256("ZcashSaplingFVFP", ak || nk || ovk)
+    fn fvk_tag(&self) -> Result<[u8; 4], OmniError> {
+        let fvk = self.to_diversifiable_fvk()?;
+
+        let fingerprint = blake2b_simd::Params::new()
+            .hash_length(32)
+            .personal(ZIP32_SAPLING_FVFP_PERSONALIZATION)
+            .hash(&fvk[..96]);
+
+        let mut tag = [0u8; 4];
+        tag.copy_from_slice(&fingerprint.as_bytes()[..4]);
+        Ok(tag)
+    }
+}
+
+/// Derive the ZIP-32 Sapling master extended spending key from a seed
+#[wasm_bindgen]
+pub fn derive_master(seed: &[u8]) -> Result<ExtendedSpendingKey, OmniError> {
+    if seed.len() < 32 || seed.len() > 252 {
+        return Err(OmniError::InvalidLength("Seed must be between 32 and 252 bytes".into()));
+    }
+
+    let i = blake2b_simd::Params::new()
+        .hash_length(64)
+        .personal(ZIP32_SAPLING_MASTER_PERSONALIZATION)
+        .hash(seed);
+    let (sk_m, c_m) = i.as_bytes().split_at(32);
+
+    let mut chain_code = [0u8; 32];
+    chain_code.copy_from_slice(c_m);
+
+    Ok(ExtendedSpendingKey {
+        depth: 0,
+        parent_fvk_tag: [0u8; 4],
+        child_index: 0,
+        chain_code,
+        expsk: expand_spending_key(sk_m),
+        dk: prf_expand(sk_m, &[0x10]),
+    })
+}
+
+/// Derive the serialized hardened child of a serialized extended spending key
+#[wasm_bindgen]
+pub fn derive_child(parent: &[u8], index: u32) -> Result<Vec<u8>, OmniError> {
+    Ok(ExtendedSpendingKey::from_bytes(parent)?.derive_child(index)?.to_bytes())
+}
+
+/// The keys of one ZIP-32 account, derived from the seed once. Each typed key
+/// is computed on first use and cached, so repeated calls skip the scalar
+/// decoding and fixed-base multiplications. The key and its caches are wiped
+/// when the account is dropped (or freed from JavaScript).
+#[wasm_bindgen]
+pub struct Account {
+    key: ExtendedSpendingKey,
+    spending_key: OnceCell<SpendingKey>,
+    full_viewing_key: OnceCell<FullViewingKey>,
+    ivk: OnceCell<CachedScalar>,
+    addresses: RefCell<BTreeMap<u32, PaymentAddress>>,
+}
+
+/// A cached secret scalar, overwritten with zero when wiped
+#[derive(Clone, Copy, Default)]
+struct CachedScalar(Fr);
+
+impl DefaultIsZeroes for CachedScalar {}
+
+impl Zeroize for Account {
+    fn zeroize(&mut self) {
+        self.key.zeroize();
+        // SpendingKey wipes itself when the cache drops it
+        self.spending_key.take();
+        self.full_viewing_key.take();
+        if let Some(ivk) = self.ivk.get_mut() {
+            ivk.zeroize();
+        }
+        self.ivk.take();
+        self.addresses.get_mut().clear();
+    }
+}
+
+impl Drop for Account {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for Account {}
+
+#[wasm_bindgen]
+impl Account {
+    /// The account's extended spending key at `m/32'/coin_type'/account'`
+    #[wasm_bindgen(getter)]
+    pub fn extended_spending_key(&self) -> ExtendedSpendingKey {
+        self.key.clone()
+    }
+
+    /// The expanded spending key, as `generate_spending_key_with_account` returns it
+    pub fn spending_key(&self) -> Result<SpendingKey, OmniError> {
+        if let Some(key) = self.spending_key.get() {
+            return Ok(key.clone());
+        }
+        let key = SpendingKey::from_bytes(&self.key.expsk)?;
+        Ok(self.spending_key.get_or_init(|| key).clone())
+    }
+
+    /// The account's diversifiable full viewing key, with the dk of its
+    /// extended key
+    pub fn full_viewing_key(&self) -> Result<FullViewingKey, OmniError> {
+        if let Some(fvk) = self.full_viewing_key.get() {
+            return Ok(fvk.clone());
+        }
+        let fvk = FullViewingKey::from_bytes(&self.key.to_diversifiable_fvk()?)?;
+        Ok(self.full_viewing_key.get_or_init(|| fvk).clone())
+    }
+
+    /// The payment address at the first diversifier index >= `diversifier_index`
+    /// whose FF1-AES256_dk diversifier is valid, as `derive_payment_address`
+    /// derives it
+    pub fn address(&self, diversifier_index: u32) -> Result<PaymentAddress, OmniError> {
+        if let Some(address) = self.addresses.borrow().get(&diversifier_index) {
+            return Ok(address.clone());
+        }
+
+        let ivk = match self.ivk.get() {
+            Some(ivk) => ivk.0,
+            None => {
+                let fvk = self.full_viewing_key()?;
+                self.ivk.get_or_init(|| CachedScalar(crh_ivk(&fvk.ak(), &fvk.nk()))).0
+            }
+        };
+        let (diversifier, _) = find_ff1_diversifier(&self.key.dk, diversifier_index.into())?;
+        let address = PaymentAddress::from_diversifier(&ivk, diversifier)?;
+
+        self.addresses.borrow_mut().insert(diversifier_index, address.clone());
+        Ok(address)
+    }
+}
+
+/// Derive the ZIP-32 account `m/32'/coin_type'/account'` for `network` in one
+/// call, with its typed keys and addresses computed lazily from the result
+#[wasm_bindgen]
+pub fn derive_account_keys(seed: &[u8], account: u32, network: Network) -> Result<Account, OmniError> {
+    let key = derive_master(seed)?
+        .derive_child(32)?
+        .derive_child(network.coin_type())?
+        .derive_child(account)?;
+
+    Ok(Account {
+        key,
+        spending_key: OnceCell::new(),
+        full_viewing_key: OnceCell::new(),
+        ivk: OnceCell::new(),
+        addresses: RefCell::new(BTreeMap::new()),
+    })
+}
+
+/// Truncated PRF^expand(key, [tag] || data), used for child ovk and dk
+fn prf_expand_tagged(key: &[u8], tag: u8, data: &[u8]) -> [u8; 32] {
+    let mut t = Vec::with_capacity(1 + data.len());
+    t.push(tag);
+    t.extend_from_slice(data);
+    prf_expand(key, &t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{derive_diversifiable_fvk, derive_payment_address, generate_spending_key_with_account};
+
+    fn assert_zeroize_on_drop<T: Zeroize + ZeroizeOnDrop>() {}
+
+    fn test_seed() -> Vec<u8> {
+        (0u8..32).collect()
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        hex::encode(bytes)
+    }
+
+    #[test]
+    fn keys_zeroize() {
+        assert_zeroize_on_drop::<ExtendedSpendingKey>();
+        assert_zeroize_on_drop::<Account>();
+
+        let mut key = derive_master(&test_seed()).unwrap();
+        key.zeroize();
+        assert!(key.to_bytes().iter().all(|&b| b == 0));
+
+        let mut account = derive_account_keys(&test_seed(), 0, Network::Mainnet).unwrap();
+        account.address(0).unwrap();
+        account.zeroize();
+        assert!(account.extended_spending_key().to_bytes().iter().all(|&b| b == 0));
+        assert!(account.spending_key.get().is_none());
+        assert!(account.ivk.get().is_none());
+        assert!(account.addresses.borrow().is_empty());
+    }
+
+    #[test]
+    fn account_matches_individual_functions() {
+        let account = derive_account_keys(&test_seed(), 3, Network::Testnet).unwrap();
+        let expsk = generate_spending_key_with_account(&test_seed(), 1, 3).unwrap();
+        let dfvk = account.extended_spending_key().to_diversifiable_fvk().unwrap();
+
+        // Cached and freshly computed values agree, on first and repeated use
+        for _ in 0..2 {
+            assert_eq!(account.spending_key().unwrap().to_bytes(), expsk);
+            assert_eq!(account.full_viewing_key().unwrap().to_bytes(), dfvk);
+            assert_eq!(derive_diversifiable_fvk(&expsk).unwrap()[..96], dfvk[..96]);
+            for index in [0, 2, 9] {
+                assert_eq!(
+                    account.address(index).unwrap().encode(Network::Testnet).unwrap(),
+                    derive_payment_address(&dfvk, index, Network::Testnet).unwrap()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn master_key_matches_zip32_vector() {
+        let master = derive_master(&test_seed()).unwrap();
+        assert_eq!(master.depth(), 0);
+        assert_eq!(master.child_index(), 0);
+
+        let expsk = master.expanded_spending_key();
+        assert_eq!(hex(&expsk[..32]), "b6c00c93d36032b9a268e99e86a860776560bf0e83c1a10b51f607c954742506");
+        assert_eq!(hex(&expsk[64..]), "395884890323b9d4933c021db89bcf767df21977b2ff0683848321a4df4afb21");
+        assert_eq!(hex(&master.dk()), "77c17cb75b7796afb39f0f3e91c924607da56fa9a20e283509bc8a3ef996a172");
+
+        let dfvk = master.to_diversifiable_fvk().unwrap();
+        assert_eq!(hex(&dfvk[..32]), "93442e5feffbff16e7217202dc7306729ffffe85af5683bce2642e3eeb5d3871");
+        assert_eq!(hex(&dfvk[32..64]), "dce8e7edece04b8950417f85ba57691b783c45b1a27422db1693dceb67b10106");
+        assert_eq!(
+            hex(&crh_ivk(&dfvk[..32], &dfvk[32..64]).to_bytes()),
+            "4847a130e799d3dbea36a1c16467d621fb2d80e30b3b1d1a426893415dad6601"
+        );
+
+        // The first two diversifier indices are valid, the third is not
+        let (d0, _) = find_ff1_diversifier(&master.dk(), 0).unwrap();
+        assert_eq!(hex(&d0), "d8621b981cf300e9d4cc89");
+        let (d1, _) = find_ff1_diversifier(&master.dk(), 1).unwrap();
+        assert_eq!(hex(&d1), "48ea17a199c84bd1baa5d4");
+        assert_eq!(find_ff1_diversifier(&master.dk(), 2).unwrap().1, 5);
+    }
+
+    #[test]
+    fn child_keys_chain_and_round_trip() {
+        let master = derive_master(&test_seed()).unwrap();
+        let child = master.derive_child(1).unwrap();
+        let grandchild = child.derive_child(2).unwrap();
+
+        assert_eq!((child.depth(), child.child_index()), (1, 1 | HARDENED));
+        assert_eq!((grandchild.depth(), grandchild.child_index()), (2, 2 | HARDENED));
+        assert_eq!(child.parent_fvk_tag(), master.fvk_tag().unwrap());
+        assert_eq!(grandchild.parent_fvk_tag(), child.fvk_tag().unwrap());
+        assert_ne!(child.expanded_spending_key(), master.expanded_spending_key());
+
+        let bytes = grandchild.to_bytes();
+        assert_eq!(derive_child(&child.to_bytes(), 2).unwrap(), bytes);
+        assert_eq!(ExtendedSpendingKey::from_bytes(&bytes).unwrap().to_bytes(), bytes);
+        assert!(master.derive_child(HARDENED).is_err());
+    }
+}